@@ -72,7 +72,8 @@ use codex_core::default_client::get_codex_user_agent;
 use codex_core::exec::ExecParams;
 use codex_core::exec_env::create_env;
 use codex_core::get_platform_sandbox;
-use codex_core::git_info::git_diff_to_remote;
+use codex_core::git_info::GitDiffToRemoteOptions;
+use codex_core::git_info::git_diff_to_remote_checked;
 use codex_core::protocol::ApplyPatchApprovalRequestEvent;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
@@ -1188,19 +1189,21 @@ impl CodexMessageProcessor {
     }
 
     async fn git_diff_to_origin(&self, request_id: RequestId, cwd: PathBuf) {
-        let diff = git_diff_to_remote(&cwd).await;
+        let diff = git_diff_to_remote_checked(&cwd, &GitDiffToRemoteOptions::default()).await;
         match diff {
-            Some(value) => {
+            Ok(value) => {
                 let response = GitDiffToRemoteResponse {
                     sha: value.sha,
                     diff: value.diff,
                 };
                 self.outgoing.send_response(request_id, response).await;
             }
-            None => {
+            Err(err) => {
                 let error = JSONRPCErrorError {
                     code: INVALID_REQUEST_ERROR_CODE,
-                    message: format!("failed to compute git diff to remote for cwd: {cwd:?}"),
+                    message: format!(
+                        "failed to compute git diff to remote for cwd: {cwd:?}: {err}"
+                    ),
                     data: None,
                 };
                 self.outgoing.send_error(request_id, error).await;
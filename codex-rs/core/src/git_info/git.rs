@@ -1,21 +1,124 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::ops::RangeInclusive;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Instant;
 
 use codex_app_server_protocol::GitSha;
 use codex_protocol::protocol::GitInfo;
+use codex_protocol::protocol::RemoteUrl;
+use codex_protocol::protocol::SubmoduleState;
+use codex_protocol::protocol::SubmoduleStatus;
 use futures::future::join_all;
 use serde::Deserialize;
 use serde::Serialize;
+use thiserror::Error;
 use tokio::process::Command;
 use tokio::time::Duration as TokioDuration;
 use tokio::time::timeout;
 
 use crate::revision_control::git::get_git_repo_root;
+use crate::revision_control::git::git_program;
+use crate::revision_control::git::uses_lfs;
 
 /// Timeout for git commands to prevent freezing on large repositories
 const GIT_COMMAND_TIMEOUT: TokioDuration = TokioDuration::from_secs(5);
 
+/// Environment for git subprocesses whose caller does not pass its own
+/// [`GitCommandEnv`]; fixed once by [`set_git_command_env`].
+static GIT_COMMAND_ENV: OnceLock<GitCommandEnv> = OnceLock::new();
+
+/// Most remote branches containing `HEAD` that [`branch_ancestry`] takes from each
+/// remote, so repositories with thousands of matching branches stay fast.
+const MAX_REMOTE_BRANCHES_PER_REMOTE: usize = 20;
+
+/// Never block on credential prompts, and skip optional index lock/refresh work.
+const DEFAULT_GIT_COMMAND_ENV: &[(&str, &str)] =
+    &[("GIT_TERMINAL_PROMPT", "0"), ("GIT_OPTIONAL_LOCKS", "0")];
+
+/// Why a git command produced no output.
+#[derive(Debug, Error)]
+pub enum GitCommandError {
+    #[error("git command timed out")]
+    Timeout,
+    #[error("failed to run git: {0}")]
+    SpawnFailed(#[from] std::io::Error),
+}
+
+/// Environment variables set on a git subprocess: [`DEFAULT_GIT_COMMAND_ENV`] plus
+/// overrides such as `GIT_SSH_COMMAND`, which take precedence over the defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GitCommandEnv {
+    overrides: HashMap<String, String>,
+}
+
+impl GitCommandEnv {
+    pub fn new(overrides: HashMap<String, String>) -> Self {
+        Self { overrides }
+    }
+
+    /// The defaults with the overrides applied on top.
+    pub fn vars(&self) -> HashMap<String, String> {
+        let mut env: HashMap<String, String> = DEFAULT_GIT_COMMAND_ENV
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect();
+        env.extend(self.overrides.clone());
+        env
+    }
+}
+
+/// Retry policy and environment for [`run_git_command_with_options`].
+#[derive(Clone, Debug)]
+pub struct GitCommandOptions {
+    /// Extra attempts made when git fails because another process holds a lock file.
+    pub retries: u32,
+    /// Delay before the first retry; doubled after each further attempt.
+    pub backoff: TokioDuration,
+    /// Environment for the command; the process-wide one from [`set_git_command_env`]
+    /// when unset.
+    pub env: Option<GitCommandEnv>,
+}
+
+impl Default for GitCommandOptions {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff: TokioDuration::from_millis(100),
+            env: None,
+        }
+    }
+}
+
+/// Why [`git_diff_to_remote_checked`] produced no diff.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GitDiffToRemoteError {
+    #[error("no commit shared with a remote was found to diff against")]
+    Unavailable,
+    #[error(
+        "no commit shared with a remote was found in this shallow clone; run `git fetch --unshallow` to fetch the full history"
+    )]
+    ShallowHistory,
+}
+
+/// Tuning for [`git_diff_to_remote_with`].
+#[derive(Clone, Debug, Default)]
+pub struct GitDiffToRemoteOptions {
+    /// Remote checked first when looking for the closest sha; `origin` when unset.
+    pub preferred_remote: Option<String>,
+    /// Untracked files larger than this many bytes are replaced by a short placeholder
+    /// instead of their full patch. `None` includes every file in full.
+    pub max_untracked_file_size: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GitDiffToRemote {
     pub sha: GitSha,
@@ -27,57 +130,673 @@ pub struct GitDiffToRemote {
 /// Uses timeouts to prevent freezing on large repositories.
 /// All git commands (except the initial repo check) run in parallel for better performance.
 pub(super) async fn collect_git_info(cwd: &Path) -> Option<GitInfo> {
+    try_collect_git_info(cwd).await.ok().flatten()
+}
+
+/// Opt-in extras for [`collect_git_info_with`] that are too expensive to collect by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GitInfoOptions {
+    /// Fill [`GitInfo::ignored_count`]; needs a `git status` walk of ignored directories.
+    pub count_ignored: bool,
+}
+
+/// Like [`collect_git_info`], additionally collecting the fields enabled in `options`.
+pub(super) async fn collect_git_info_with(cwd: &Path, options: &GitInfoOptions) -> Option<GitInfo> {
+    let mut git_info = collect_git_info(cwd).await?;
+    if options.count_ignored {
+        git_info.ignored_count = ignored_count(cwd).await;
+    }
+    Some(git_info)
+}
+
+/// Number of `!!` entries in `git status --porcelain --ignored`, where a directory whose
+/// contents are all ignored is a single entry.
+async fn ignored_count(cwd: &Path) -> Option<usize> {
+    let output =
+        run_git_command_with_timeout(&["status", "--porcelain", "--ignored", "-z"], cwd).await?;
+    if !output.status.success() {
+        return None;
+    }
+    let count = output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter(|entry| entry.starts_with(b"!! "))
+        .count();
+    Some(count)
+}
+
+/// Like [`collect_git_info`] but reports when git could not be run or timed out on the
+/// initial repository check, instead of treating it the same as "not a repository".
+pub(super) async fn try_collect_git_info(cwd: &Path) -> Result<Option<GitInfo>, GitCommandError> {
     // Check if we're in a git repository first
-    let is_git_repo = run_git_command_with_timeout(&["rev-parse", "--git-dir"], cwd)
+    let is_git_repo = run_git_command(&["rev-parse", "--git-dir"], cwd)
         .await?
         .status
         .success();
 
     if !is_git_repo {
-        return None;
+        return Ok(None);
     }
 
     // Run all git info collection commands in parallel
-    let (commit_result, branch_result, url_result) = tokio::join!(
-        run_git_command_with_timeout(&["rev-parse", "HEAD"], cwd),
-        run_git_command_with_timeout(&["rev-parse", "--abbrev-ref", "HEAD"], cwd),
-        run_git_command_with_timeout(&["remote", "get-url", "origin"], cwd)
-    );
+    let outputs = join_all(
+        GIT_INFO_COMMANDS
+            .iter()
+            .map(|(_, args)| run_git_command_with_timeout(args, cwd)),
+    )
+    .await;
+    let mut git_info = git_info_from_outputs(outputs, cwd);
+
+    // Fall back to the first remote's URL when there is no origin
+    if git_info.repository_url.is_none() {
+        git_info.repository_url = first_remote_url(cwd).await;
+    }
+    git_info.parsed_remote = git_info
+        .repository_url
+        .as_deref()
+        .and_then(RemoteUrl::parse);
+
+    Ok(Some(git_info))
+}
+
+/// Blocking counterpart of [`collect_git_info`] for synchronous callers such as trust
+/// resolution. Commands run on scoped threads, each bounded by the same timeout.
+pub(super) fn collect_git_info_blocking(cwd: &Path) -> Option<GitInfo> {
+    let is_git_repo = run_git_command_blocking(&["rev-parse", "--git-dir"], cwd)?
+        .status
+        .success();
+    if !is_git_repo {
+        return None;
+    }
+
+    let outputs = std::thread::scope(|scope| {
+        let handles: Vec<_> = GIT_INFO_COMMANDS
+            .iter()
+            .map(|(_, args)| scope.spawn(move || run_git_command_blocking(args, cwd)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().ok().flatten())
+            .collect::<Vec<_>>()
+    });
+    let mut git_info = git_info_from_outputs(outputs, cwd);
+
+    if git_info.repository_url.is_none() {
+        git_info.repository_url = first_remote_url_blocking(cwd);
+    }
+    git_info.parsed_remote = git_info
+        .repository_url
+        .as_deref()
+        .and_then(RemoteUrl::parse);
+
+    Some(git_info)
+}
 
+/// [`GitInfo`] field filled from the output of one of [`GIT_INFO_COMMANDS`].
+enum GitInfoField {
+    CommitHash,
+    /// Abbreviated commit hash, as git itself would display it
+    ShortCommitHash,
+    Branch,
+    /// Fails when the branch has no tracking branch configured
+    Upstream,
+    RepositoryUrl,
+    /// Fails when HEAD is not exactly on a tag
+    Tag,
+    IsShallow,
+    Submodules,
+}
+
+/// Git commands whose outputs make up a [`GitInfo`], each paired with the field it fills.
+static GIT_INFO_COMMANDS: [(GitInfoField, &[&str]); 8] = [
+    (GitInfoField::CommitHash, &["rev-parse", "HEAD"]),
+    (
+        GitInfoField::ShortCommitHash,
+        &["rev-parse", "--short", "HEAD"],
+    ),
+    (GitInfoField::Branch, &["rev-parse", "--abbrev-ref", "HEAD"]),
+    (
+        GitInfoField::Upstream,
+        &[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ],
+    ),
+    (
+        GitInfoField::RepositoryUrl,
+        &["remote", "get-url", "origin"],
+    ),
+    (
+        GitInfoField::Tag,
+        &["describe", "--tags", "--exact-match", "HEAD"],
+    ),
+    (
+        GitInfoField::IsShallow,
+        &["rev-parse", "--is-shallow-repository"],
+    ),
+    (
+        GitInfoField::Submodules,
+        &["submodule", "status", "--recursive"],
+    ),
+];
+
+/// Build a [`GitInfo`] from the outputs of [`GIT_INFO_COMMANDS`], given in the same
+/// order. A missing or failed output leaves its field unset; `repository_url` is left for
+/// the caller to fill from another remote when there is no origin, and `parsed_remote` to
+/// parse once it is final.
+fn git_info_from_outputs(outputs: Vec<Option<std::process::Output>>, cwd: &Path) -> GitInfo {
     let mut git_info = GitInfo {
         commit_hash: None,
+        short_commit_hash: None,
         branch: None,
+        upstream: None,
         repository_url: None,
+        parsed_remote: None,
+        tag: None,
+        uses_lfs: get_git_repo_root(cwd).is_some_and(|root| uses_lfs(&root)),
+        is_shallow: false,
+        ignored_count: None,
+        submodules: Vec::new(),
     };
+    for ((field, _), output) in GIT_INFO_COMMANDS.iter().zip(outputs) {
+        let Some(stdout) = output.and_then(successful_stdout) else {
+            continue;
+        };
+        match field {
+            GitInfoField::CommitHash => git_info.commit_hash = Some(stdout),
+            GitInfoField::ShortCommitHash => git_info.short_commit_hash = Some(stdout),
+            GitInfoField::Branch => git_info.branch = Some(stdout).filter(|b| b != "HEAD"),
+            GitInfoField::Upstream => git_info.upstream = Some(stdout),
+            GitInfoField::RepositoryUrl => git_info.repository_url = Some(stdout),
+            GitInfoField::Tag => git_info.tag = Some(stdout),
+            GitInfoField::IsShallow => git_info.is_shallow = stdout == "true",
+            GitInfoField::Submodules => git_info.submodules = parse_submodule_status(&stdout),
+        }
+    }
+    git_info
+}
 
-    // Process commit hash
-    if let Some(output) = commit_result
-        && output.status.success()
-        && let Ok(hash) = String::from_utf8(output.stdout)
-    {
-        git_info.commit_hash = Some(hash.trim().to_string());
+/// Trimmed stdout of a successful git command.
+fn successful_stdout(output: std::process::Output) -> Option<String> {
+    if !output.status.success() {
+        return None;
     }
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|stdout| stdout.trim().to_string())
+}
 
-    // Process branch name
-    if let Some(output) = branch_result
-        && output.status.success()
-        && let Ok(branch) = String::from_utf8(output.stdout)
-    {
-        let branch = branch.trim();
-        if branch != "HEAD" {
-            git_info.branch = Some(branch.to_string());
+/// Parse `git submodule status` output, where each line is a state prefix
+/// (space, `+`, `-`, or `U`) followed by `<sha> <path>` and an optional `(<describe>)`.
+fn parse_submodule_status(status: &str) -> Vec<SubmoduleStatus> {
+    status
+        .lines()
+        .filter_map(|line| {
+            let mut chars = line.chars();
+            let state = match chars.next()? {
+                ' ' => SubmoduleState::InSync,
+                '-' => SubmoduleState::Uninitialized,
+                _ => SubmoduleState::Modified,
+            };
+            let (sha, rest) = chars.as_str().split_once(' ')?;
+            let path = match rest.rsplit_once(" (") {
+                Some((path, _)) => path,
+                None => rest,
+            };
+            Some(SubmoduleStatus {
+                path: path.to_string(),
+                sha: sha.to_string(),
+                state,
+            })
+        })
+        .collect()
+}
+
+/// Tree object hash of `path` (relative to `cwd`) as it currently exists in the working
+/// tree, including untracked files that are not ignored.
+///
+/// The working tree is staged into a temporary index seeded from `HEAD`, so the real
+/// index is untouched. When nothing under `path` differs from `HEAD`, the result equals
+/// `git rev-parse HEAD:<path>`. Returns `None` outside a repository or when `path`
+/// contains no files.
+pub(super) async fn subtree_hash(cwd: &Path, path: &Path) -> Option<String> {
+    let index_dir = tempfile::tempdir().ok()?;
+    let index_file = index_dir.path().join("index");
+    let path = path.to_str()?;
+
+    let has_head = run_git_command_with_timeout(&["rev-parse", "--verify", "--quiet", "HEAD"], cwd)
+        .await?
+        .status
+        .success();
+    if has_head {
+        let read_tree =
+            run_git_command_with_index(&["read-tree", "HEAD"], cwd, &index_file).await?;
+        if !read_tree.status.success() {
+            return None;
         }
     }
 
-    // Process repository URL
-    if let Some(output) = url_result
-        && output.status.success()
-        && let Ok(url) = String::from_utf8(output.stdout)
-    {
-        git_info.repository_url = Some(url.trim().to_string());
+    let add = run_git_command_with_index(&["add", "--all", "--", path], cwd, &index_file).await?;
+    if !add.status.success() {
+        return None;
     }
 
-    Some(git_info)
+    let write_tree = run_git_command_with_index(&["write-tree"], cwd, &index_file).await?;
+    if !write_tree.status.success() {
+        return None;
+    }
+    let tree = String::from_utf8(write_tree.stdout).ok()?;
+
+    // `<tree>:./<path>` resolves `path` relative to `cwd` rather than the repository root.
+    let spec = format!("{}:./{path}", tree.trim());
+    let output =
+        run_git_command_with_timeout(&["rev-parse", "--verify", "--quiet", &spec], cwd).await?;
+    if !output.status.success() {
+        return None;
+    }
+    let hash = String::from_utf8(output.stdout).ok()?;
+    Some(hash.trim().to_string())
+}
+
+/// Every path in the index of the repository rooted at `repo_root`, relative to that root.
+pub(super) async fn tracked_files(repo_root: &Path) -> io::Result<Vec<PathBuf>> {
+    let output = run_git_command_with_timeout(&["ls-files", "--cached", "-z"], repo_root)
+        .await
+        .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "git ls-files timed out"))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git ls-files failed with status {}",
+            output.status
+        )));
+    }
+    Ok(output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+        .collect())
+}
+
+/// Whether `path` (relative to `repo_root`, or absolute) is a tracked file or a directory
+/// holding tracked files, per `git ls-files --error-unmatch`.
+pub(super) async fn is_tracked(repo_root: &Path, path: &Path) -> io::Result<bool> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path is not valid UTF-8"))?;
+    let path = if path.is_empty() { "." } else { path };
+    let output = run_git_command_with_timeout(
+        &["ls-files", "--cached", "--error-unmatch", "--", path],
+        repo_root,
+    )
+    .await
+    .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "git ls-files timed out"))?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => Err(io::Error::other(format!(
+            "git ls-files failed with status {}",
+            output.status
+        ))),
+    }
+}
+
+/// Commit a blamed line was last changed in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlameCommit {
+    Committed(String),
+    /// The line differs from `HEAD` in the working tree (git reports an all-zero sha).
+    Uncommitted,
+}
+
+/// One line of [`blame`] output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlameLine {
+    pub sha: BlameCommit,
+    pub author: String,
+    /// Unix timestamp (seconds since epoch) of the author time.
+    pub timestamp: i64,
+    /// 1-based line number in the current file.
+    pub line_number: usize,
+    /// Line contents without the trailing newline.
+    pub line: String,
+}
+
+/// Blame lines `line_range` (1-based, inclusive) of `path` as it is in the working tree,
+/// via `git blame --porcelain`.
+pub(super) async fn blame(
+    cwd: &Path,
+    path: &Path,
+    line_range: RangeInclusive<usize>,
+) -> io::Result<Vec<BlameLine>> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path is not valid UTF-8"))?;
+    let range = format!("{},{}", line_range.start(), line_range.end());
+    let output =
+        run_git_command_with_timeout(&["blame", "--porcelain", "-L", &range, "--", path], cwd)
+            .await
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "git blame timed out"))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git blame failed with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(parse_blame_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parse `git blame --porcelain`. Each line starts with `<sha> <orig> <final> [<count>]`;
+/// commit details such as `author` follow only the first time a sha appears, and the
+/// line contents come last, prefixed with a tab.
+fn parse_blame_porcelain(text: &str) -> Vec<BlameLine> {
+    let mut commits: HashMap<&str, (String, i64)> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(&str, usize)> = None;
+    for line in text.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            let Some((sha, line_number)) = current.take() else {
+                continue;
+            };
+            let (author, timestamp) = commits.get(sha).cloned().unwrap_or_default();
+            let sha = if sha.bytes().all(|byte| byte == b'0') {
+                BlameCommit::Uncommitted
+            } else {
+                BlameCommit::Committed(sha.to_string())
+            };
+            lines.push(BlameLine {
+                sha,
+                author,
+                timestamp,
+                line_number,
+                line: content.to_string(),
+            });
+            continue;
+        }
+
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match (current, key) {
+            (Some((sha, _)), "author") => commits.entry(sha).or_default().0 = value.to_string(),
+            (Some((sha, _)), "author-time") => {
+                commits.entry(sha).or_default().1 = value.parse().unwrap_or(0);
+            }
+            (None, sha) if sha.len() >= 40 && sha.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                let line_number = value
+                    .split(' ')
+                    .nth(1)
+                    .and_then(|final_line| final_line.parse().ok())
+                    .unwrap_or(0);
+                current = Some((sha, line_number));
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Whether the repository has any active hooks: files in its hooks directory (which
+/// honors `core.hooksPath`) that are not `*.sample` templates and, on Unix, are
+/// executable. `false` outside a repository.
+pub(super) async fn has_hooks(cwd: &Path) -> bool {
+    let Some(hooks_dir) = run_git_command_with_timeout(&["rev-parse", "--git-path", "hooks"], cwd)
+        .await
+        .and_then(successful_stdout)
+    else {
+        return false;
+    };
+    let Ok(mut entries) = tokio::fs::read_dir(cwd.join(hooks_dir)).await else {
+        return false;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.file_name().to_string_lossy().ends_with(".sample") {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        if metadata.is_file() && is_executable(&metadata) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+/// Git for Windows runs hooks regardless of file permissions.
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// How git sees a single path in the working tree, as reported by [`path_status`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathStatus {
+    /// In the index (for a directory: contains at least one indexed file).
+    Tracked,
+    /// Not in the index and matched by an ignore rule.
+    Ignored,
+    /// Not in the index and not ignored.
+    Untracked,
+    /// Does not exist in the working tree.
+    Missing,
+}
+
+/// Classify `path` (relative to `cwd`, or absolute) as tracked, ignored, untracked, or
+/// missing. A path that was deleted from disk counts as missing even if it is still in the
+/// index. Returns `None` outside a repository, for paths outside it, or on error/timeout.
+pub(super) async fn path_status(cwd: &Path, path: &Path) -> Option<PathStatus> {
+    let path_str = path.to_str()?;
+    let exists = tokio::fs::symlink_metadata(cwd.join(path)).await.is_ok();
+
+    let (tracked, check_ignore) = tokio::join!(
+        is_tracked(cwd, path),
+        run_git_command_with_timeout(&["check-ignore", "--quiet", "--", path_str], cwd),
+    );
+    let check_ignore = check_ignore?;
+    // check-ignore exits 0 when ignored, 1 when not, and 128 on errors such as
+    // running outside a repository or being handed a path outside it.
+    let ignored = match check_ignore.status.code() {
+        Some(0) => true,
+        Some(1) => false,
+        _ => return None,
+    };
+    if !exists {
+        return Some(PathStatus::Missing);
+    }
+
+    let status = if tracked.ok()? {
+        PathStatus::Tracked
+    } else if ignored {
+        PathStatus::Ignored
+    } else {
+        PathStatus::Untracked
+    };
+    Some(status)
+}
+
+/// Multi-step git operation that is paused waiting for the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitOperationState {
+    /// `progress` is `None` when git's state files could not be read.
+    Rebase {
+        progress: Option<RebaseProgress>,
+    },
+    Merge,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+
+/// Position within an in-progress rebase, e.g. step 3 of 7.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RebaseProgress {
+    pub current: usize,
+    pub total: usize,
+}
+
+pub(super) async fn git_operation_state(cwd: &Path) -> Option<GitOperationState> {
+    let git_dir = absolute_git_dir(cwd).await?;
+    operation_state_in(&git_dir)
+}
+
+/// Operation in progress in the repository whose git directory is `git_dir`, judged by the
+/// marker files git leaves there.
+fn operation_state_in(git_dir: &Path) -> Option<GitOperationState> {
+    if git_dir.join("rebase-merge").is_dir() || git_dir.join("rebase-apply").is_dir() {
+        return Some(GitOperationState::Rebase {
+            progress: read_rebase_progress(&git_dir),
+        });
+    }
+    [
+        ("MERGE_HEAD", GitOperationState::Merge),
+        ("CHERRY_PICK_HEAD", GitOperationState::CherryPick),
+        ("REVERT_HEAD", GitOperationState::Revert),
+        ("BISECT_LOG", GitOperationState::Bisect),
+    ]
+    .into_iter()
+    .find_map(|(marker, state)| git_dir.join(marker).is_file().then_some(state))
+}
+
+/// Operation whose intermediate state makes snapshotting or restoring the working tree
+/// unsafe, as reported by [`pending_operation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PendingOperation {
+    Merge,
+    Rebase,
+    CherryPick,
+}
+
+impl fmt::Display for PendingOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Merge => "merge",
+            Self::Rebase => "rebase",
+            Self::CherryPick => "cherry-pick",
+        })
+    }
+}
+
+/// Blocking check for a merge, rebase, or cherry-pick in progress, by the marker files git
+/// leaves in its directory. Returns `None` when none is pending or outside a repository.
+pub(super) fn pending_operation(cwd: &Path) -> Option<PendingOperation> {
+    let output = run_git_command_blocking(&["rev-parse", "--absolute-git-dir"], cwd)?;
+    let git_dir = PathBuf::from(successful_stdout(output)?);
+
+    match operation_state_in(&git_dir)? {
+        GitOperationState::Rebase { .. } => Some(PendingOperation::Rebase),
+        GitOperationState::Merge => Some(PendingOperation::Merge),
+        GitOperationState::CherryPick => Some(PendingOperation::CherryPick),
+        GitOperationState::Revert | GitOperationState::Bisect => None,
+    }
+}
+
+pub(super) async fn rebase_progress(cwd: &Path) -> Option<RebaseProgress> {
+    let git_dir = absolute_git_dir(cwd).await?;
+    read_rebase_progress(&git_dir)
+}
+
+/// Interactive and merge-based rebases keep their position in `rebase-merge/{msgnum,end}`;
+/// patch-based rebases (`git am` style) use `rebase-apply/{next,last}`.
+fn read_rebase_progress(git_dir: &Path) -> Option<RebaseProgress> {
+    let read_number = |path: PathBuf| -> Option<usize> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+    [
+        ("rebase-merge", "msgnum", "end"),
+        ("rebase-apply", "next", "last"),
+    ]
+    .into_iter()
+    .find_map(|(dir, current, total)| {
+        let dir = git_dir.join(dir);
+        Some(RebaseProgress {
+            current: read_number(dir.join(current))?,
+            total: read_number(dir.join(total))?,
+        })
+    })
+}
+
+async fn absolute_git_dir(cwd: &Path) -> Option<PathBuf> {
+    let output = run_git_command_with_timeout(&["rev-parse", "--absolute-git-dir"], cwd).await?;
+    if !output.status.success() {
+        return None;
+    }
+    let git_dir = String::from_utf8(output.stdout).ok()?;
+    Some(PathBuf::from(git_dir.trim()))
+}
+
+/// Hosting service behind a repository's remote URL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoteHost {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    /// Self-hosted or unrecognized hosts, and repositories without a remote URL.
+    Other,
+}
+
+pub(super) fn remote_host_kind(git_info: &GitInfo) -> RemoteHost {
+    let remote = git_info
+        .repository_url
+        .as_deref()
+        .and_then(RemoteUrl::parse);
+    match remote.as_ref().map(|remote| remote.host.as_str()) {
+        Some("github.com") => RemoteHost::GitHub,
+        Some("gitlab.com") => RemoteHost::GitLab,
+        Some("bitbucket.org") => RemoteHost::Bitbucket,
+        _ => RemoteHost::Other,
+    }
+}
+
+/// Reduce a remote URL to `host/owner/repo` so the HTTPS and SSH spellings of the same
+/// repository compare equal: the scheme, user, port, and a trailing `.git` or `/` are
+/// dropped and the result is lowercased. URLs [`RemoteUrl::parse`] does not recognize
+/// (local paths) are kept as written, minus any trailing `/`.
+fn normalize_remote_url(url: &str) -> String {
+    match RemoteUrl::parse(url) {
+        Some(remote) => format!("{}/{}/{}", remote.host, remote.owner, remote.repo).to_lowercase(),
+        None => url.trim().trim_end_matches('/').to_string(),
+    }
+}
+
+/// Whether `origin` and `upstream` both exist and point at different repositories.
+pub(super) async fn is_fork(cwd: &Path) -> Option<bool> {
+    get_git_repo_root(cwd)?;
+    let (origin, upstream) = tokio::join!(remote_url(cwd, "origin"), remote_url(cwd, "upstream"));
+    match (origin, upstream) {
+        (Some(origin), Some(upstream)) => {
+            Some(normalize_remote_url(&origin) != normalize_remote_url(&upstream))
+        }
+        _ => Some(false),
+    }
+}
+
+/// URL of the first configured remote, used when `origin` does not exist.
+async fn first_remote_url(cwd: &Path) -> Option<String> {
+    let remote = get_git_remotes(cwd).await?.into_iter().next()?;
+    remote_url(cwd, &remote).await
+}
+
+/// Blocking counterpart of [`first_remote_url`].
+fn first_remote_url_blocking(cwd: &Path) -> Option<String> {
+    let output = run_git_command_blocking(&["remote"], cwd)?;
+    let remote = parse_remotes(output, "origin")?.into_iter().next()?;
+    successful_stdout(run_git_command_blocking(
+        &["remote", "get-url", &remote],
+        cwd,
+    )?)
+}
+
+async fn remote_url(cwd: &Path, remote: &str) -> Option<String> {
+    successful_stdout(run_git_command_with_timeout(&["remote", "get-url", remote], cwd).await?)
 }
 
 /// A minimal commit summary entry used for pickers (subject + timestamp + sha).
@@ -88,12 +807,132 @@ pub struct CommitLogEntry {
     pub timestamp: i64,
     /// Single-line subject of the commit message.
     pub subject: String,
+    /// Commit message after the subject line, which may span several lines; empty when
+    /// the message is only a subject.
+    #[serde(default)]
+    pub body: String,
+    /// Notes attached to the commit; only fetched by [`recent_commits_with_notes`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// Restricts which commits [`recent_commits_filtered`] returns.
+#[derive(Clone, Debug, Default)]
+pub struct RecentCommitsFilter {
+    /// Only include commits whose author matches this pattern (`git log --author`).
+    pub author: Option<String>,
+    /// Only include commits touching at least one of these paths.
+    pub paths: Vec<PathBuf>,
 }
 
 /// Return the last `limit` commits reachable from HEAD for the current branch.
-/// Each entry contains the SHA, commit timestamp (seconds), and subject line.
+/// Each entry contains the SHA, commit timestamp (seconds), subject line, and body.
 /// Returns an empty vector if not in a git repo or on error/timeout.
 pub(super) async fn recent_commits(cwd: &Path, limit: usize) -> Vec<CommitLogEntry> {
+    recent_commits_page(cwd, 0, limit).await
+}
+
+/// Like [`recent_commits`] but skips the `skip` most recent commits first.
+pub(super) async fn recent_commits_page(
+    cwd: &Path,
+    skip: usize,
+    limit: usize,
+) -> Vec<CommitLogEntry> {
+    log_commits(
+        cwd,
+        None,
+        skip,
+        limit,
+        &RecentCommitsFilter::default(),
+        None,
+    )
+    .await
+}
+
+/// Like [`recent_commits`] but also reads each commit's notes from `notes_ref`
+/// (git's default notes ref when `None`).
+pub(super) async fn recent_commits_with_notes(
+    cwd: &Path,
+    limit: usize,
+    notes_ref: Option<&str>,
+) -> Vec<CommitLogEntry> {
+    let notes_arg = match notes_ref {
+        Some(notes_ref) => format!("--notes={notes_ref}"),
+        None => "--notes".to_string(),
+    };
+    log_commits(
+        cwd,
+        None,
+        0,
+        limit,
+        &RecentCommitsFilter::default(),
+        Some(&notes_arg),
+    )
+    .await
+}
+
+/// Cursor-based paging: up to `limit` commits reachable from `start_ref`, plus the sha to
+/// pass as `start_ref` for the next page (the first parent of the last entry). The cursor
+/// is `None` once history is exhausted. Errors and timeouts yield an empty page.
+pub(super) async fn recent_commits_from(
+    cwd: &Path,
+    start_ref: &str,
+    limit: usize,
+) -> (Vec<CommitLogEntry>, Option<String>) {
+    // Refuse anything git could parse as an option instead of a revision.
+    if start_ref.is_empty() || start_ref.starts_with('-') {
+        return (Vec::new(), None);
+    }
+    let entries = log_commits(
+        cwd,
+        Some(start_ref),
+        0,
+        limit,
+        &RecentCommitsFilter::default(),
+        None,
+    )
+    .await;
+    if entries.len() < limit.max(1) {
+        return (entries, None);
+    }
+
+    let next_cursor = match entries.last() {
+        Some(last) => first_parent(cwd, &last.sha).await,
+        None => None,
+    };
+    (entries, next_cursor)
+}
+
+async fn first_parent(cwd: &Path, sha: &str) -> Option<String> {
+    let parent = format!("{sha}^");
+    let output =
+        run_git_command_with_timeout(&["rev-parse", "--verify", "--quiet", &parent], cwd).await?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+}
+
+/// Like [`recent_commits`] but only returns commits matching `filter`.
+pub(super) async fn recent_commits_filtered(
+    cwd: &Path,
+    limit: usize,
+    filter: &RecentCommitsFilter,
+) -> Vec<CommitLogEntry> {
+    log_commits(cwd, None, 0, limit, filter, None).await
+}
+
+async fn log_commits(
+    cwd: &Path,
+    start_ref: Option<&str>,
+    skip: usize,
+    limit: usize,
+    filter: &RecentCommitsFilter,
+    notes_arg: Option<&str>,
+) -> Vec<CommitLogEntry> {
     // Ensure we're in a git repo first to avoid noisy errors.
     let Some(out) = run_git_command_with_timeout(&["rev-parse", "--git-dir"], cwd).await else {
         return Vec::new();
@@ -102,12 +941,42 @@ pub(super) async fn recent_commits(cwd: &Path, limit: usize) -> Vec<CommitLogEnt
         return Vec::new();
     }
 
-    let fmt = "%H%x1f%ct%x1f%s"; // <sha> <US> <commit_time> <US> <subject>
+    // <sha> <US> <commit_time> <US> <subject> <US> <body> [<US> <notes>] <RS>; the body
+    // and notes may span lines, so records are split on RS rather than on newlines.
+    let fmt = if notes_arg.is_some() {
+        "%H%x1f%ct%x1f%s%x1f%b%x1f%N%x1e"
+    } else {
+        "%H%x1f%ct%x1f%s%x1f%b%x1e"
+    };
     let n = limit.max(1).to_string();
-    let Some(log_out) =
-        run_git_command_with_timeout(&["log", "-n", &n, &format!("--pretty=format:{fmt}")], cwd)
-            .await
-    else {
+    let skip = format!("--skip={skip}");
+    let pretty = format!("--pretty=format:{fmt}");
+    let author = filter
+        .author
+        .as_ref()
+        .map(|author| format!("--author={author}"));
+    let paths: Vec<String> = filter
+        .paths
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+
+    let mut args = vec!["log", "-n", &n, &skip, &pretty];
+    if let Some(author) = author.as_deref() {
+        args.push(author);
+    }
+    if let Some(notes_arg) = notes_arg {
+        args.push(notes_arg);
+    }
+    if let Some(start_ref) = start_ref {
+        args.push(start_ref);
+    }
+    if !paths.is_empty() {
+        args.push("--");
+        args.extend(paths.iter().map(String::as_str));
+    }
+
+    let Some(log_out) = run_git_command_with_timeout(&args, cwd).await else {
         return Vec::new();
     };
     if !log_out.status.success() {
@@ -116,8 +985,8 @@ pub(super) async fn recent_commits(cwd: &Path, limit: usize) -> Vec<CommitLogEnt
 
     let text = String::from_utf8_lossy(&log_out.stdout);
     let mut entries: Vec<CommitLogEntry> = Vec::new();
-    for line in text.lines() {
-        let mut parts = line.split('\u{001f}');
+    for record in text.split('\u{001e}') {
+        let mut parts = record.split('\u{001f}');
         let sha = parts.next().unwrap_or("").trim();
         let ts_s = parts.next().unwrap_or("").trim();
         let subject = parts.next().unwrap_or("").trim();
@@ -125,47 +994,342 @@ pub(super) async fn recent_commits(cwd: &Path, limit: usize) -> Vec<CommitLogEnt
             continue;
         }
         let timestamp = ts_s.parse::<i64>().unwrap_or(0);
+        let body = parts.next().unwrap_or("").trim();
+        let notes = parts
+            .next()
+            .map(str::trim)
+            .filter(|notes| !notes.is_empty())
+            .map(str::to_string);
         entries.push(CommitLogEntry {
             sha: sha.to_string(),
             timestamp,
             subject: subject.to_string(),
+            body: body.to_string(),
+            notes,
         });
     }
 
     entries
 }
 
+/// Count the distinct contributors across all refs, honoring `.mailmap`.
+///
+/// Walks the entire history via `git shortlog`, so this is only computed on request.
+/// Returns None if not in a git repo or on error/timeout.
+pub(super) async fn contributor_count(cwd: &Path) -> Option<usize> {
+    let output = run_git_command_with_timeout(&["shortlog", "-sne", "--all"], cwd).await?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Each line looks like "<count>\t<name> <<email>>".
+    let text = String::from_utf8_lossy(&output.stdout);
+    let contributors: HashSet<&str> = text
+        .lines()
+        .filter_map(|line| line.split_once('\t').map(|(_, identity)| identity.trim()))
+        .filter(|identity| !identity.is_empty())
+        .collect();
+    Some(contributors.len())
+}
+
+/// Lines changed by one author, as aggregated by [`author_stats`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorStat {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Commits, insertions, and deletions per author on the current branch, optionally
+/// limited to commits newer than `since` (any date `git log --since` accepts).
+///
+/// Authors are identified after `.mailmap` is applied. Binary changes count as no lines.
+/// This walks history with `--numstat`, so it is only computed on request. Sorted by
+/// commit count, most active first. Returns an empty vector on error or timeout.
+pub(super) async fn author_stats(cwd: &Path, since: Option<String>) -> Vec<AuthorStat> {
+    let since = since.map(|since| format!("--since={since}"));
+    // <SOH><author name><US><author email>, followed by the commit's numstat lines.
+    let mut args = vec!["log", "--numstat", "--format=%x01%aN%x1f%aE"];
+    if let Some(since) = since.as_deref() {
+        args.push(since);
+    }
+    let Some(output) = run_git_command_with_timeout(&args, cwd).await else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    parse_author_stats(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_author_stats(log: &str) -> Vec<AuthorStat> {
+    let mut stats: Vec<AuthorStat> = Vec::new();
+    let mut current: Option<usize> = None;
+    for line in log.lines() {
+        if let Some(header) = line.strip_prefix('\u{1}') {
+            let (name, email) = header.split_once('\u{1f}').unwrap_or((header, ""));
+            let index = match stats
+                .iter()
+                .position(|stat| stat.name == name && stat.email == email)
+            {
+                Some(index) => index,
+                None => {
+                    stats.push(AuthorStat {
+                        name: name.to_string(),
+                        email: email.to_string(),
+                        commits: 0,
+                        insertions: 0,
+                        deletions: 0,
+                    });
+                    stats.len() - 1
+                }
+            };
+            stats[index].commits += 1;
+            current = Some(index);
+            continue;
+        }
+
+        // "<insertions>\t<deletions>\t<path>", with "-" counts for binary files.
+        let (Some(index), Some((insertions, rest))) = (current, line.split_once('\t')) else {
+            continue;
+        };
+        let deletions = rest
+            .split_once('\t')
+            .map_or(rest, |(deletions, _)| deletions);
+        stats[index].insertions += insertions.parse::<usize>().unwrap_or(0);
+        stats[index].deletions += deletions.parse::<usize>().unwrap_or(0);
+    }
+
+    stats.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+    stats
+}
+
 /// Returns the closest git sha to HEAD that is on a remote as well as the diff to that sha.
 pub(super) async fn git_diff_to_remote(cwd: &Path) -> Option<GitDiffToRemote> {
-    get_git_repo_root(cwd)?;
+    git_diff_to_remote_for(cwd, "origin").await
+}
 
-    let remotes = get_git_remotes(cwd).await?;
-    let branches = branch_ancestry(cwd).await?;
-    let base_sha = find_closest_sha(cwd, &branches, &remotes).await?;
-    let diff = diff_against_sha(cwd, &base_sha).await?;
+/// Like [`git_diff_to_remote`] but checks `preferred_remote` first when looking for the
+/// closest remote sha. Falls back to prioritizing `origin` if that remote does not exist.
+pub(super) async fn git_diff_to_remote_for(
+    cwd: &Path,
+    preferred_remote: &str,
+) -> Option<GitDiffToRemote> {
+    let options = GitDiffToRemoteOptions {
+        preferred_remote: Some(preferred_remote.to_string()),
+        ..Default::default()
+    };
+    git_diff_to_remote_with(cwd, &options).await
+}
 
-    Some(GitDiffToRemote {
+pub(super) async fn git_diff_to_remote_with(
+    cwd: &Path,
+    options: &GitDiffToRemoteOptions,
+) -> Option<GitDiffToRemote> {
+    git_diff_to_remote_checked(cwd, options).await.ok()
+}
+
+pub(super) async fn git_diff_to_remote_checked(
+    cwd: &Path,
+    options: &GitDiffToRemoteOptions,
+) -> Result<GitDiffToRemote, GitDiffToRemoteError> {
+    use GitDiffToRemoteError::Unavailable;
+
+    get_git_repo_root(cwd).ok_or(Unavailable)?;
+
+    let preferred_remote = options.preferred_remote.as_deref().unwrap_or("origin");
+    let remotes = get_git_remotes_preferring(cwd, preferred_remote)
+        .await
+        .ok_or(Unavailable)?;
+    let branches = branch_ancestry(cwd).await.ok_or(Unavailable)?;
+    let Some(base_sha) = find_closest_sha(cwd, &branches, &remotes).await else {
+        // In a shallow clone the shared commit may simply not have been fetched.
+        return Err(if is_shallow_repository(cwd).await {
+            GitDiffToRemoteError::ShallowHistory
+        } else {
+            Unavailable
+        });
+    };
+    let diff = diff_against_sha(cwd, &base_sha, options.max_untracked_file_size)
+        .await
+        .ok_or(Unavailable)?;
+
+    Ok(GitDiffToRemote {
         sha: base_sha,
         diff,
     })
 }
 
+async fn is_shallow_repository(cwd: &Path) -> bool {
+    run_git_command_with_timeout(&["rev-parse", "--is-shallow-repository"], cwd)
+        .await
+        .is_some_and(|output| is_true_output(&output))
+}
+
+/// Whether a successful git command printed `true`, as boolean queries like
+/// `rev-parse --is-shallow-repository` do.
+fn is_true_output(output: &std::process::Output) -> bool {
+    output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true"
+}
+
+pub(super) fn set_git_command_env(env: GitCommandEnv) -> Result<(), GitCommandEnv> {
+    GIT_COMMAND_ENV.set(env)
+}
+
+pub(super) fn git_command_env() -> &'static GitCommandEnv {
+    GIT_COMMAND_ENV.get_or_init(GitCommandEnv::default)
+}
+
+pub(super) fn git_command(args: &[&str], cwd: &Path) -> Command {
+    git_command_with(&git_program(), git_command_env(), args, cwd)
+}
+
+/// Like [`git_command`] but runs `program` with `env` instead of the resolved
+/// [`git_program`] and the process-wide environment.
+pub(super) fn git_command_with(
+    program: &OsStr,
+    env: &GitCommandEnv,
+    args: &[&str],
+    cwd: &Path,
+) -> Command {
+    let mut command = Command::new(program);
+    command.args(args).current_dir(cwd).envs(env.vars());
+    command
+}
+
 /// Run a git command with a timeout to prevent blocking on large repositories
 async fn run_git_command_with_timeout(args: &[&str], cwd: &Path) -> Option<std::process::Output> {
-    let result = timeout(
-        GIT_COMMAND_TIMEOUT,
-        Command::new("git").args(args).current_dir(cwd).output(),
-    )
-    .await;
+    run_git_command(args, cwd).await.ok()
+}
+
+/// Like [`run_git_command_with_timeout`] but retries with exponential backoff while git
+/// reports lock contention (e.g. `Unable to create '.git/index.lock'`), which happens
+/// on network filesystems. Any other failure is returned immediately.
+pub(super) async fn run_git_command_with_options(
+    args: &[&str],
+    cwd: &Path,
+    options: &GitCommandOptions,
+) -> Option<std::process::Output> {
+    let env = match &options.env {
+        Some(env) => env,
+        None => git_command_env(),
+    };
+    let mut backoff = options.backoff;
+    let mut attempt = 0;
+    loop {
+        let command = git_command_with(&git_program(), env, args, cwd);
+        let output = run_with_timeout(command, GIT_COMMAND_TIMEOUT).await.ok()?;
+        if output.status.success()
+            || attempt >= options.retries
+            || !is_lock_contention(&String::from_utf8_lossy(&output.stderr))
+        {
+            return Some(output);
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = backoff.saturating_mul(2);
+        attempt += 1;
+    }
+}
+
+fn is_lock_contention(stderr: &str) -> bool {
+    stderr.contains("Unable to create") && stderr.contains(".lock")
+}
+
+/// Blocking counterpart of [`run_git_command_with_timeout`]: the child is polled until it
+/// exits and killed once [`GIT_COMMAND_TIMEOUT`] passes.
+fn run_git_command_blocking(args: &[&str], cwd: &Path) -> Option<std::process::Output> {
+    let mut child = std::process::Command::new(git_program())
+        .args(args)
+        .current_dir(cwd)
+        .envs(git_command_env().vars())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    // Drain both pipes while waiting so a chatty git cannot block on a full pipe.
+    let stdout = drain_pipe(child.stdout.take());
+    let stderr = drain_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + GIT_COMMAND_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+
+    Some(std::process::Output {
+        status,
+        stdout: stdout.join().ok()?,
+        stderr: stderr.join().ok()?,
+    })
+}
+
+fn drain_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+async fn run_git_command(
+    args: &[&str],
+    cwd: &Path,
+) -> Result<std::process::Output, GitCommandError> {
+    run_with_timeout(git_command(args, cwd), GIT_COMMAND_TIMEOUT).await
+}
+
+/// Like [`run_git_command_with_timeout`] but operates on the index at `index_file`.
+async fn run_git_command_with_index(
+    args: &[&str],
+    cwd: &Path,
+    index_file: &Path,
+) -> Option<std::process::Output> {
+    let mut command = git_command(args, cwd);
+    command.env("GIT_INDEX_FILE", index_file);
+    run_with_timeout(command, GIT_COMMAND_TIMEOUT).await.ok()
+}
 
-    match result {
-        Ok(Ok(output)) => Some(output),
-        _ => None, // Timeout or error
+pub(super) async fn run_with_timeout(
+    mut command: Command,
+    limit: TokioDuration,
+) -> Result<std::process::Output, GitCommandError> {
+    // Don't leave a hung git process behind when the timeout fires.
+    command.kill_on_drop(true);
+    match timeout(limit, command.output()).await {
+        Ok(output) => Ok(output?),
+        Err(_) => Err(GitCommandError::Timeout),
     }
 }
 
 async fn get_git_remotes(cwd: &Path) -> Option<Vec<String>> {
-    let output = run_git_command_with_timeout(&["remote"], cwd).await?;
+    get_git_remotes_preferring(cwd, "origin").await
+}
+
+/// List remotes with `preferred` first, or `origin` first when `preferred` is not configured.
+async fn get_git_remotes_preferring(cwd: &Path, preferred: &str) -> Option<Vec<String>> {
+    parse_remotes(
+        run_git_command_with_timeout(&["remote"], cwd).await?,
+        preferred,
+    )
+}
+
+/// Remote names from `git remote` output, ordered as in [`get_git_remotes_preferring`].
+fn parse_remotes(output: std::process::Output, preferred: &str) -> Option<Vec<String>> {
     if !output.status.success() {
         return None;
     }
@@ -174,9 +1338,14 @@ async fn get_git_remotes(cwd: &Path) -> Option<Vec<String>> {
         .lines()
         .map(str::to_string)
         .collect();
-    if let Some(pos) = remotes.iter().position(|r| r == "origin") {
-        let origin = remotes.remove(pos);
-        remotes.insert(0, origin);
+    let front = if remotes.iter().any(|r| r == preferred) {
+        preferred
+    } else {
+        "origin"
+    };
+    if let Some(pos) = remotes.iter().position(|r| r == front) {
+        let remote = remotes.remove(pos);
+        remotes.insert(0, remote);
     }
     Some(remotes)
 }
@@ -184,10 +1353,20 @@ async fn get_git_remotes(cwd: &Path) -> Option<Vec<String>> {
 /// Attempt to determine the repository's default branch name.
 ///
 /// Preference order:
-/// 1) The symbolic ref at `refs/remotes/<remote>/HEAD` for the first remote (origin prioritized)
-/// 2) `git remote show <remote>` parsed for "HEAD branch: <name>"
-/// 3) Local fallback to existing `main` or `master` if present
-async fn get_default_branch(cwd: &Path) -> Option<String> {
+/// 1) The `refs/remotes/origin/HEAD` file, read from disk without running git so it
+///    works offline
+/// 2) The symbolic ref at `refs/remotes/<remote>/HEAD` for the first remote (origin prioritized)
+/// 3) `git remote show <remote>` parsed for "HEAD branch: <name>"
+/// 4) Local fallback to existing `main` or `master` if present
+pub(super) async fn get_default_branch(cwd: &Path) -> Option<String> {
+    // A clone records origin's default branch in a plain file, which is readable
+    // even when the remote is unreachable or no longer configured.
+    if let Some(git_dir) = absolute_git_dir(cwd).await
+        && let Some(name) = read_remote_head_file(&git_dir, "origin")
+    {
+        return Some(name);
+    }
+
     // Prefer the first remote (with origin prioritized)
     let remotes = get_git_remotes(cwd).await.unwrap_or_default();
     for remote in remotes {
@@ -232,6 +1411,23 @@ async fn get_default_branch(cwd: &Path) -> Option<String> {
     get_default_branch_local(cwd).await
 }
 
+/// Read `refs/remotes/<remote>/HEAD` straight from disk. The file holds a symbolic
+/// ref such as `ref: refs/remotes/origin/main`; linked worktrees find it through
+/// the `commondir` file of their git dir.
+fn read_remote_head_file(git_dir: &Path, remote: &str) -> Option<String> {
+    let common_dir = match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    };
+    let contents =
+        std::fs::read_to_string(common_dir.join("refs/remotes").join(remote).join("HEAD")).ok()?;
+    let target = contents.trim().strip_prefix("ref:")?.trim();
+    target
+        .strip_prefix(&format!("refs/remotes/{remote}/"))
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
 /// Attempt to determine the repository's default branch name from local branches.
 async fn get_default_branch_local(cwd: &Path) -> Option<String> {
     for candidate in ["main", "master"] {
@@ -256,7 +1452,7 @@ async fn get_default_branch_local(cwd: &Path) -> Option<String> {
 
 /// Build an ancestry of branches starting at the current branch and ending at the
 /// repository's default branch (if determinable)..
-async fn branch_ancestry(cwd: &Path) -> Option<Vec<String>> {
+pub(super) async fn branch_ancestry(cwd: &Path) -> Option<Vec<String>> {
     // Discover current branch (ignore detached HEAD by treating it as None)
     let current_branch = run_git_command_with_timeout(&["rev-parse", "--abbrev-ref", "HEAD"], cwd)
         .await
@@ -289,9 +1485,11 @@ async fn branch_ancestry(cwd: &Path) -> Option<Vec<String>> {
     // Expand candidates: include any remote branches that already contain HEAD.
     // This addresses cases where we're on a new local-only branch forked from a
     // remote branch that isn't the repository default. We prioritize remotes in
-    // the order returned by get_git_remotes (origin first).
+    // the order returned by get_git_remotes (origin first), taking at most
+    // MAX_REMOTE_BRANCHES_PER_REMOTE new branches from each.
     let remotes = get_git_remotes(cwd).await.unwrap_or_default();
     for remote in remotes {
+        let mut taken = 0;
         if let Some(output) = run_git_command_with_timeout(
             &[
                 "for-each-ref",
@@ -306,6 +1504,9 @@ async fn branch_ancestry(cwd: &Path) -> Option<Vec<String>> {
             && let Ok(text) = String::from_utf8(output.stdout)
         {
             for line in text.lines() {
+                if taken == MAX_REMOTE_BRANCHES_PER_REMOTE {
+                    break;
+                }
                 let short = line.trim();
                 // Expect format like: "origin/feature"; extract the branch path after "remote/"
                 if let Some(stripped) = short.strip_prefix(&format!("{remote}/"))
@@ -314,6 +1515,7 @@ async fn branch_ancestry(cwd: &Path) -> Option<Vec<String>> {
                 {
                     seen.insert(stripped.to_string());
                     ancestry.push(stripped.to_string());
+                    taken += 1;
                 }
             }
         }
@@ -430,7 +1632,15 @@ async fn find_closest_sha(cwd: &Path, branches: &[String], remotes: &[String]) -
     closest_sha.map(|(sha, _)| sha)
 }
 
-async fn diff_against_sha(cwd: &Path, sha: &GitSha) -> Option<String> {
+/// Diff the working tree against `sha`, appending untracked files as additions.
+///
+/// Untracked files honor `.gitignore`, `.git/info/exclude`, and `core.excludesFile`.
+/// Those larger than `max_untracked_file_size` bytes get an [`omitted_file_placeholder`].
+async fn diff_against_sha(
+    cwd: &Path,
+    sha: &GitSha,
+    max_untracked_file_size: Option<u64>,
+) -> Option<String> {
     let output =
         run_git_command_with_timeout(&["diff", "--no-textconv", "--no-ext-diff", &sha.0], cwd)
             .await?;
@@ -458,6 +1668,11 @@ async fn diff_against_sha(cwd: &Path, sha: &GitSha) -> Option<String> {
             let null_device: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
             let futures_iter = untracked.into_iter().map(|file| async move {
                 let file_owned = file;
+                if let Some(size) =
+                    oversized_file(&cwd.join(&file_owned), max_untracked_file_size).await
+                {
+                    return Some(omitted_file_placeholder(&file_owned, size));
+                }
                 let args_vec: Vec<&str> = vec![
                     "diff",
                     "--no-textconv",
@@ -469,15 +1684,16 @@ async fn diff_against_sha(cwd: &Path, sha: &GitSha) -> Option<String> {
                     null_device,
                     &file_owned,
                 ];
-                run_git_command_with_timeout(&args_vec, cwd).await
+                let extra = run_git_command_with_timeout(&args_vec, cwd).await?;
+                if extra.status.code().is_some_and(|c| c == 0 || c == 1) {
+                    String::from_utf8(extra.stdout).ok()
+                } else {
+                    None
+                }
             });
             let results = join_all(futures_iter).await;
             for extra in results.into_iter().flatten() {
-                if extra.status.code().is_some_and(|c| c == 0 || c == 1)
-                    && let Ok(s) = String::from_utf8(extra.stdout)
-                {
-                    diff.push_str(&s);
-                }
+                diff.push_str(&extra);
             }
         }
     }
@@ -485,6 +1701,111 @@ async fn diff_against_sha(cwd: &Path, sha: &GitSha) -> Option<String> {
     Some(diff)
 }
 
+/// Working tree changes against `HEAD`, with untracked files appended as additions.
+/// In a repository without commits every file is diffed against the empty tree.
+pub(super) async fn workspace_diff(cwd: &Path) -> Option<String> {
+    let head = run_git_command_with_timeout(&["rev-parse", "--verify", "--quiet", "HEAD"], cwd)
+        .await
+        .and_then(successful_stdout);
+    let base = match head {
+        Some(head) => head,
+        None => {
+            let null_device: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
+            run_git_command_with_timeout(&["hash-object", "-t", "tree", null_device], cwd)
+                .await
+                .and_then(successful_stdout)?
+        }
+    };
+    diff_against_sha(cwd, &GitSha::new(&base), None).await
+}
+
+/// Size of `path` when it exceeds `max_size` bytes.
+async fn oversized_file(path: &Path, max_size: Option<u64>) -> Option<u64> {
+    let max_size = max_size?;
+    let size = tokio::fs::metadata(path).await.ok()?.len();
+    (size > max_size).then_some(size)
+}
+
+/// Diff stanza standing in for a file too large to include in full.
+pub fn omitted_file_placeholder(path: &str, size: u64) -> String {
+    format!(
+        "diff --git a/{path} b/{path}\nnew file mode 100644\nbinary/large file omitted ({size} bytes)\n"
+    )
+}
+
+/// Current contents of every file changed relative to `HEAD` (including untracked files),
+/// each truncated to at most `max_bytes_per_file` bytes. Paths are relative to `cwd`.
+///
+/// Deleted and binary files are skipped. In a repository without commits every file in
+/// the index counts as changed.
+pub(super) async fn changed_files_content(
+    cwd: &Path,
+    max_bytes_per_file: usize,
+) -> Vec<(PathBuf, String)> {
+    let has_head = run_git_command_with_timeout(&["rev-parse", "--verify", "--quiet", "HEAD"], cwd)
+        .await
+        .is_some_and(|output| output.status.success());
+    let tracked_args: &[&str] = if has_head {
+        &[
+            "diff",
+            "--name-only",
+            "--no-renames",
+            "--relative",
+            "-z",
+            "HEAD",
+        ]
+    } else {
+        &["ls-files", "--cached", "-z"]
+    };
+    let untracked_args = ["ls-files", "--others", "--exclude-standard", "-z"];
+    let (Some(tracked), Some(untracked)) = tokio::join!(
+        run_git_command_with_timeout(tracked_args, cwd),
+        run_git_command_with_timeout(&untracked_args, cwd),
+    ) else {
+        return Vec::new();
+    };
+    if !tracked.status.success() || !untracked.status.success() {
+        return Vec::new();
+    }
+
+    let tracked = String::from_utf8_lossy(&tracked.stdout);
+    let untracked = String::from_utf8_lossy(&untracked.stdout);
+    let paths: BTreeSet<&str> = tracked
+        .split('\0')
+        .chain(untracked.split('\0'))
+        .filter(|path| !path.is_empty())
+        .collect();
+
+    let mut contents = Vec::new();
+    for path in paths {
+        let Ok(bytes) = tokio::fs::read(cwd.join(path)).await else {
+            continue;
+        };
+        if bytes.contains(&0) {
+            continue;
+        }
+        let Ok(text) = String::from_utf8(bytes) else {
+            continue;
+        };
+        contents.push((
+            PathBuf::from(path),
+            truncate_at_char_boundary(text, max_bytes_per_file),
+        ));
+    }
+    contents
+}
+
+fn truncate_at_char_boundary(mut text: String, max_bytes: usize) -> String {
+    if text.len() > max_bytes {
+        let mut end = max_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
 /// Resolve the path that should be used for trust checks. Similar to
 /// `[get_git_repo_root]`, but resolves to the root of the main
 /// repository. Handles worktrees.
@@ -493,7 +1814,7 @@ pub fn resolve_root_git_project_for_trust(cwd: &Path) -> Option<PathBuf> {
 
     // TODO: we should make this async, but it's primarily used deep in
     // callstacks of sync code, and should almost always be fast
-    let git_dir_out = std::process::Command::new("git")
+    let git_dir_out = std::process::Command::new(git_program())
         .args(["rev-parse", "--git-common-dir"])
         .current_dir(base)
         .output()
@@ -545,6 +1866,175 @@ pub(super) async fn local_git_branches(cwd: &Path) -> Vec<String> {
     branches
 }
 
+/// Returns remote-tracking branches such as `origin/feature`, sorted by name.
+/// Symbolic `<remote>/HEAD` pointers are skipped.
+pub(super) async fn remote_branches(cwd: &Path) -> Vec<String> {
+    // Depending on the git version `refname:short` renders `refs/remotes/origin/HEAD` as
+    // `origin/HEAD` or plain `origin`, so identify HEAD pointers by their symref target.
+    let Some(out) = run_git_command_with_timeout(
+        &["branch", "-r", "--format=%(refname:short) %(symref)"],
+        cwd,
+    )
+    .await
+    .filter(|out| out.status.success()) else {
+        return Vec::new();
+    };
+
+    let mut branches: Vec<String> = String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| match line.trim_end_matches('\r').split_once(' ') {
+            Some((name, "")) if !name.is_empty() => Some(name.to_string()),
+            _ => None,
+        })
+        .collect();
+    branches.sort_unstable();
+    branches
+}
+
+/// One working tree attached to the repository, as listed by `git worktree list`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorktreeInfo {
+    pub path: PathBuf,
+    /// Checked-out commit; `None` for a bare repository entry or an unborn branch.
+    pub head_sha: Option<String>,
+    /// Short name of the checked-out branch; `None` when `HEAD` is detached.
+    pub branch: Option<String>,
+    /// Locked with `git worktree lock`, so `git worktree prune` leaves it alone.
+    pub is_locked: bool,
+}
+
+/// Every worktree of the repository containing `cwd`, main worktree first.
+/// Returns an empty vector outside a repository or on error/timeout.
+pub(super) async fn list_worktrees(cwd: &Path) -> Vec<WorktreeInfo> {
+    let Some(out) = run_git_command_with_timeout(&["worktree", "list", "--porcelain"], cwd)
+        .await
+        .filter(|out| out.status.success())
+    else {
+        return Vec::new();
+    };
+    parse_worktree_list(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// Parse `git worktree list --porcelain`: blank-line separated records of `worktree <path>`
+/// followed by attribute lines such as `HEAD <sha>`, `branch <ref>`, `detached`, and
+/// `locked [<reason>]`.
+fn parse_worktree_list(text: &str) -> Vec<WorktreeInfo> {
+    let mut worktrees = Vec::new();
+    let mut current: Option<WorktreeInfo> = None;
+    for line in text.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            worktrees.extend(current.take());
+            current = Some(WorktreeInfo {
+                path: PathBuf::from(path),
+                head_sha: None,
+                branch: None,
+                is_locked: false,
+            });
+            continue;
+        }
+        let Some(worktree) = current.as_mut() else {
+            continue;
+        };
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "HEAD" => {
+                worktree.head_sha = Some(value.to_string())
+                    .filter(|sha| !sha.is_empty() && sha.bytes().any(|byte| byte != b'0'));
+            }
+            "branch" => {
+                let branch = value.strip_prefix("refs/heads/").unwrap_or(value);
+                worktree.branch = Some(branch.to_string());
+            }
+            "locked" => worktree.is_locked = true,
+            _ => {}
+        }
+    }
+    worktrees.extend(current);
+    worktrees
+}
+
+/// A local branch with its upstream and how far it has drifted from it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchTracking {
+    pub name: String,
+    /// Short name of the upstream, e.g. `origin/main`; `None` when none is configured.
+    pub upstream: Option<String>,
+    /// Commits on the branch that the upstream lacks.
+    pub ahead: usize,
+    /// Commits on the upstream that the branch lacks.
+    pub behind: usize,
+}
+
+/// Every local branch with its upstream ahead/behind counts, read from a single
+/// `git for-each-ref` call. Branches without an upstream, or whose upstream is gone,
+/// report zero for both counts. Returns an empty vector on error/timeout.
+pub(super) async fn branches_with_tracking(cwd: &Path) -> Vec<BranchTracking> {
+    let Some(out) = run_git_command_with_timeout(
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)%1f%(upstream:short)%1f%(upstream:track)",
+            "refs/heads",
+        ],
+        cwd,
+    )
+    .await
+    .filter(|out| out.status.success()) else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\u{001f}');
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            let upstream = fields.next().filter(|upstream| !upstream.is_empty());
+            let (ahead, behind) = parse_upstream_track(fields.next().unwrap_or(""));
+            Some(BranchTracking {
+                name: name.to_string(),
+                upstream: upstream.map(str::to_string),
+                ahead,
+                behind,
+            })
+        })
+        .collect()
+}
+
+/// Parse `%(upstream:track)`, e.g. `[ahead 2, behind 1]`, `[behind 3]`, or `[gone]`.
+fn parse_upstream_track(track: &str) -> (usize, usize) {
+    let track = track.trim().trim_start_matches('[').trim_end_matches(']');
+    let (mut ahead, mut behind) = (0, 0);
+    for part in track.split(", ") {
+        if let Some(count) = part.strip_prefix("ahead ") {
+            ahead = count.parse().unwrap_or(0);
+        } else if let Some(count) = part.strip_prefix("behind ") {
+            behind = count.parse().unwrap_or(0);
+        }
+    }
+    (ahead, behind)
+}
+
+/// Whether the current branch and its upstream have both gained commits the other lacks.
+/// Returns `None` when the branch has no upstream.
+pub(super) async fn is_diverged_from_upstream(cwd: &Path) -> Option<bool> {
+    let (ahead, behind) = upstream_ahead_behind(cwd).await?;
+    Some(ahead > 0 && behind > 0)
+}
+
+/// Number of commits `HEAD` is ahead of and behind its upstream (`@{upstream}`).
+async fn upstream_ahead_behind(cwd: &Path) -> Option<(usize, usize)> {
+    let output = run_git_command_with_timeout(
+        &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+        cwd,
+    )
+    .await?;
+    if !output.status.success() {
+        return None;
+    }
+    let counts = String::from_utf8(output.stdout).ok()?;
+    let (ahead, behind) = counts.trim().split_once('\t')?;
+    Some((ahead.parse().ok()?, behind.parse().ok()?))
+}
+
 /// Returns the current checked out branch name.
 pub(super) async fn current_branch_name(cwd: &Path) -> Option<String> {
     let out = run_git_command_with_timeout(&["branch", "--show-current"], cwd).await?;
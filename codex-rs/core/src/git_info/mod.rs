@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::io;
+use std::ops::RangeInclusive;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -7,12 +10,71 @@ use crate::revision_control::RevisionControlKind;
 use crate::revision_control::RevisionControlSystem;
 
 mod git;
+mod repo_diff;
 
+pub use git::AuthorStat;
+pub use git::BlameCommit;
+pub use git::BlameLine;
+pub use git::BranchTracking;
 pub use git::CommitLogEntry;
+pub use git::GitCommandEnv;
+pub use git::GitCommandError;
+pub use git::GitCommandOptions;
 pub use git::GitDiffToRemote;
+pub use git::GitDiffToRemoteError;
+pub use git::GitDiffToRemoteOptions;
+pub use git::GitInfoOptions;
+pub use git::GitOperationState;
+pub use git::PathStatus;
+pub use git::PendingOperation;
+pub use git::RebaseProgress;
+pub use git::RecentCommitsFilter;
+pub use git::RemoteHost;
+pub use git::WorktreeInfo;
+pub use git::omitted_file_placeholder;
+pub use repo_diff::DiffStat;
+pub use repo_diff::DiffTarget;
+pub use repo_diff::FileDiff;
+pub use repo_diff::FileStatus;
+pub use repo_diff::Hunk;
+pub use repo_diff::RepoDiffError;
+pub use repo_diff::RepoDiffOptions;
+pub use repo_diff::UntrackedFiles;
+pub use repo_diff::get_repo_diff;
+pub use repo_diff::get_repo_diff_in;
+pub use repo_diff::get_repo_diff_structured;
+pub use repo_diff::get_repo_diff_structured_in;
+pub use repo_diff::get_repo_diffs;
+pub use repo_diff::repo_diff_numstat;
+pub use repo_diff::stream_repo_diff;
+
+pub use codex_protocol::protocol::RemoteScheme;
+pub use codex_protocol::protocol::RemoteUrl;
 
 pub use crate::revision_control::git::get_git_repo_root;
 
+/// Set the environment for every git subprocess Codex spawns without an explicit
+/// [`GitCommandEnv`], e.g. to add `GIT_SSH_COMMAND` in sandboxed runs. Can only be set
+/// once per process, before the first git command; a later call returns `env` back.
+pub fn set_git_command_env(env: GitCommandEnv) -> Result<(), GitCommandEnv> {
+    git::set_git_command_env(env)
+}
+
+/// Environment applied to git subprocesses: the defaults plus any configured overrides.
+pub fn git_command_env() -> HashMap<String, String> {
+    git::git_command_env().vars()
+}
+
+/// Run `git <args>` in `cwd` with the standard timeout, retrying per `options` while the
+/// repository is locked by another git process. `None` if git could not be run.
+pub async fn run_git_command_with_options(
+    args: &[&str],
+    cwd: &Path,
+    options: &GitCommandOptions,
+) -> Option<std::process::Output> {
+    git::run_git_command_with_options(args, cwd, options).await
+}
+
 /// Collect repository metadata for the provided revision control backend.
 pub async fn collect_git_info(
     revision_control: &dyn RevisionControlSystem,
@@ -25,18 +87,250 @@ pub async fn collect_git_info(
     git::collect_git_info(cwd).await
 }
 
+/// Like [`collect_git_info`], also collecting the opt-in fields enabled in `options`, such
+/// as [`GitInfo::ignored_count`].
+pub async fn collect_git_info_with(
+    revision_control: &dyn RevisionControlSystem,
+    cwd: &Path,
+    options: &GitInfoOptions,
+) -> Option<GitInfo> {
+    if revision_control.kind() != RevisionControlKind::Git {
+        return None;
+    }
+
+    git::collect_git_info_with(cwd, options).await
+}
+
+/// Blocking form of [`collect_git_info`] for synchronous code such as the trust
+/// resolution callstack, which would otherwise have to start a runtime. Async callers
+/// should keep using [`collect_git_info`].
+pub fn collect_git_info_blocking(
+    revision_control: &dyn RevisionControlSystem,
+    cwd: &Path,
+) -> Option<GitInfo> {
+    if revision_control.kind() != RevisionControlKind::Git {
+        return None;
+    }
+
+    git::collect_git_info_blocking(cwd)
+}
+
+/// Hash of the tree at `path` in the current working tree, for cheap change detection: the
+/// value only changes when files under `path` do.
+pub async fn subtree_hash(cwd: &Path, path: &Path) -> Option<String> {
+    git::subtree_hash(cwd, path).await
+}
+
+/// Files in the index of the repository rooted at `repo_root`, relative to that root.
+/// Prefer [`RevisionControlSystem::tracked_files`], which works for every backend.
+pub async fn tracked_files(repo_root: &Path) -> io::Result<Vec<PathBuf>> {
+    git::tracked_files(repo_root).await
+}
+
+/// Who last changed lines `line_range` (1-based, inclusive) of `path`, relative to `cwd`.
+/// Lines edited in the working tree but not committed are [`BlameCommit::Uncommitted`].
+pub async fn blame(
+    cwd: &Path,
+    path: &Path,
+    line_range: RangeInclusive<usize>,
+) -> io::Result<Vec<BlameLine>> {
+    git::blame(cwd, path, line_range).await
+}
+
+/// Whether the repository has active (non-sample, executable) hooks, which run on
+/// ordinary `git commit`s. Snapshots are created with plumbing commands and never run them.
+pub async fn has_hooks(cwd: &Path) -> bool {
+    git::has_hooks(cwd).await
+}
+
+/// Whether `path` is tracked, ignored, untracked, or missing, in one lookup instead of
+/// separate index and ignore-rule queries.
+pub async fn path_status(cwd: &Path, path: &Path) -> Option<PathStatus> {
+    git::path_status(cwd, path).await
+}
+
+/// Multi-step operation (rebase, merge, cherry-pick, revert, bisect) currently in progress.
+pub async fn git_operation_state(cwd: &Path) -> Option<GitOperationState> {
+    git::git_operation_state(cwd).await
+}
+
+/// Merge, rebase, or cherry-pick in progress, checked synchronously so snapshot tooling
+/// can refuse to run mid-operation. [`git_operation_state`] reports the wider set of
+/// operations, including rebase progress.
+pub fn pending_operation(cwd: &Path) -> Option<PendingOperation> {
+    git::pending_operation(cwd)
+}
+
+/// Step of the in-progress rebase, or `None` when not rebasing.
+pub async fn rebase_progress(cwd: &Path) -> Option<RebaseProgress> {
+    git::rebase_progress(cwd).await
+}
+
+/// Classify the hosting service of `git_info.repository_url` by its host name.
+pub fn remote_host_kind(git_info: &GitInfo) -> RemoteHost {
+    git::remote_host_kind(git_info)
+}
+
+/// Like [`collect_git_info`] but distinguishes "not a Git repository" (`Ok(None)`) from git
+/// being missing or timing out (`Err`), so callers can tell the user why info is absent.
+pub async fn try_collect_git_info(
+    revision_control: &dyn RevisionControlSystem,
+    cwd: &Path,
+) -> Result<Option<GitInfo>, GitCommandError> {
+    if revision_control.kind() != RevisionControlKind::Git {
+        return Ok(None);
+    }
+
+    git::try_collect_git_info(cwd).await
+}
+
 pub async fn recent_commits(cwd: &Path, limit: usize) -> Vec<CommitLogEntry> {
     git::recent_commits(cwd, limit).await
 }
 
+/// Like [`recent_commits`] but fills [`CommitLogEntry::notes`] from `notes_ref`, or from
+/// git's default notes ref (`refs/notes/commits`) when `None`.
+pub async fn recent_commits_with_notes(
+    cwd: &Path,
+    limit: usize,
+    notes_ref: Option<&str>,
+) -> Vec<CommitLogEntry> {
+    git::recent_commits_with_notes(cwd, limit, notes_ref).await
+}
+
+/// A window of history for lazy loading: skips the `skip` most recent commits and returns
+/// up to `limit` after them.
+pub async fn recent_commits_page(cwd: &Path, skip: usize, limit: usize) -> Vec<CommitLogEntry> {
+    git::recent_commits_page(cwd, skip, limit).await
+}
+
+/// Page through history from `start_ref`: returns up to `limit` commits plus the cursor to
+/// pass as `start_ref` for the next page, or `None` when there are no more commits.
+pub async fn recent_commits_from(
+    cwd: &Path,
+    start_ref: &str,
+    limit: usize,
+) -> (Vec<CommitLogEntry>, Option<String>) {
+    git::recent_commits_from(cwd, start_ref, limit).await
+}
+
+/// Like [`recent_commits`] but restricted to commits by an author and/or touching paths.
+pub async fn recent_commits_filtered(
+    cwd: &Path,
+    limit: usize,
+    filter: &RecentCommitsFilter,
+) -> Vec<CommitLogEntry> {
+    git::recent_commits_filtered(cwd, limit, filter).await
+}
+
+/// Number of distinct commit authors across all refs (after `.mailmap`). This walks
+/// the full history, so callers should only request it when needed.
+pub async fn contributor_count(cwd: &Path) -> Option<usize> {
+    git::contributor_count(cwd).await
+}
+
+/// Commits and lines added/removed per author (after `.mailmap`), optionally only for
+/// commits newer than `since`. Walks the history with `--numstat`, so only call it on demand.
+pub async fn author_stats(cwd: &Path, since: Option<String>) -> Vec<AuthorStat> {
+    git::author_stats(cwd, since).await
+}
+
 pub async fn git_diff_to_remote(cwd: &Path) -> Option<GitDiffToRemote> {
     git::git_diff_to_remote(cwd).await
 }
 
+/// Like [`git_diff_to_remote`] but prefers `preferred_remote` over `origin` when picking the
+/// remote sha to diff against. Falls back to the origin-first order if it does not exist.
+pub async fn git_diff_to_remote_for(cwd: &Path, preferred_remote: &str) -> Option<GitDiffToRemote> {
+    git::git_diff_to_remote_for(cwd, preferred_remote).await
+}
+
+/// Like [`git_diff_to_remote`] with the remote preference and untracked-file size cap
+/// taken from `options`.
+pub async fn git_diff_to_remote_with(
+    cwd: &Path,
+    options: &GitDiffToRemoteOptions,
+) -> Option<GitDiffToRemote> {
+    git::git_diff_to_remote_with(cwd, options).await
+}
+
+/// Like [`git_diff_to_remote_with`] but explains a missing diff. In particular, a shallow
+/// clone whose history does not reach a remote commit yields
+/// [`GitDiffToRemoteError::ShallowHistory`], so callers can suggest `git fetch --unshallow`.
+pub async fn git_diff_to_remote_checked(
+    cwd: &Path,
+    options: &GitDiffToRemoteOptions,
+) -> Result<GitDiffToRemote, GitDiffToRemoteError> {
+    git::git_diff_to_remote_checked(cwd, options).await
+}
+
+/// Current contents of each changed or untracked file, truncated to `max_bytes_per_file`.
+/// Gives full context for small files where a diff is less useful; binary files are skipped.
+pub async fn changed_files_content(
+    cwd: &Path,
+    max_bytes_per_file: usize,
+) -> Vec<(PathBuf, String)> {
+    git::changed_files_content(cwd, max_bytes_per_file).await
+}
+
+/// Whether `path`, relative to `repo_root`, is tracked by git (for a directory: whether
+/// it holds any tracked file).
+pub async fn is_tracked(repo_root: &Path, path: &Path) -> io::Result<bool> {
+    git::is_tracked(repo_root, path).await
+}
+
+/// Uncommitted changes in the working tree (staged, unstaged, and untracked) as a
+/// unified diff against `HEAD`. `None` when `cwd` is not in a repository or git fails.
+pub async fn workspace_diff(cwd: &Path) -> Option<String> {
+    git::workspace_diff(cwd).await
+}
+
+/// The repository's default branch name (e.g. `main`), for picking a PR base.
+///
+/// Preference order:
+/// 1) The `.git/refs/remotes/origin/HEAD` file, read directly so it works offline
+/// 2) The symbolic ref at `refs/remotes/<remote>/HEAD` for each remote, origin first
+/// 3) The `HEAD branch:` reported by `git remote show <remote>`
+/// 4) A local `main` or `master` branch, if one exists
+pub async fn default_branch(cwd: &Path) -> Option<String> {
+    git::get_default_branch(cwd).await
+}
+
 pub async fn local_git_branches(cwd: &Path) -> Vec<String> {
     git::local_git_branches(cwd).await
 }
 
+/// Remote-tracking branches such as `origin/feature`, excluding `<remote>/HEAD` pointers.
+pub async fn remote_branches(cwd: &Path) -> Vec<String> {
+    git::remote_branches(cwd).await
+}
+
+/// Every worktree of the repository containing `cwd` (main worktree first) with its
+/// checked-out commit, branch, and lock state. Empty outside a repository.
+pub async fn list_worktrees(cwd: &Path) -> Vec<WorktreeInfo> {
+    git::list_worktrees(cwd).await
+}
+
+/// Every local branch with its upstream and ahead/behind counts, for branch pickers.
+/// Uses one `git for-each-ref` call rather than a `rev-list` per branch.
+pub async fn branches_with_tracking(cwd: &Path) -> Vec<BranchTracking> {
+    git::branches_with_tracking(cwd).await
+}
+
+/// Whether the current branch and its upstream have diverged (each has commits the other
+/// lacks). `None` when the branch has no upstream.
+pub async fn is_diverged_from_upstream(cwd: &Path) -> Option<bool> {
+    git::is_diverged_from_upstream(cwd).await
+}
+
+/// Whether the repository is a fork checkout: both `origin` and `upstream` remotes exist
+/// and their URLs name different repositories. URLs are compared after normalization, so
+/// `https://github.com/o/r.git` and `git@github.com:o/r` count as the same repository.
+/// `None` outside a Git repository.
+pub async fn is_fork(cwd: &Path) -> Option<bool> {
+    git::is_fork(cwd).await
+}
+
 pub async fn current_branch_name(cwd: &Path) -> Option<String> {
     git::current_branch_name(cwd).await
 }
@@ -51,6 +345,8 @@ mod tests {
     use crate::revision_control::DetectedRevisionControl;
     use crate::revision_control::RevisionControlKind;
     use codex_protocol::protocol::GitInfo;
+    use codex_protocol::protocol::SubmoduleState;
+    use codex_protocol::protocol::SubmoduleStatus;
     use pretty_assertions::assert_eq;
     use serde_json::Value;
     use std::path::Path;
@@ -74,6 +370,28 @@ mod tests {
             .unwrap();
     }
 
+    fn commit_as(repo_path: &Path, name: &str, email: &str, file: &str) {
+        std::fs::write(repo_path.join(file), name).unwrap();
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["add", file])
+            .output()
+            .unwrap();
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args([
+                "-c",
+                &format!("user.name={name}"),
+                "-c",
+                &format!("user.email={email}"),
+                "commit",
+                "-m",
+                file,
+            ])
+            .output()
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn collect_git_info_non_git_directory() {
         let temp_dir = tempdir().unwrap();
@@ -84,6 +402,108 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[tokio::test]
+    async fn try_collect_git_info_reports_spawn_failure() {
+        let temp_dir = tempdir().unwrap();
+        let missing = temp_dir.path().join("missing");
+        let backend = git_backend(missing.clone());
+
+        let result = try_collect_git_info(&backend, &missing).await;
+
+        assert!(matches!(result, Err(GitCommandError::SpawnFailed(_))));
+        assert!(collect_git_info(&backend, &missing).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn try_collect_git_info_distinguishes_non_repository() {
+        let temp_dir = tempdir().unwrap();
+        let backend = git_backend(temp_dir.path().to_path_buf());
+
+        let result = try_collect_git_info(&backend, temp_dir.path()).await;
+
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn git_commands_report_timeouts() {
+        let temp_dir = tempdir().unwrap();
+        let command = git::git_command(&["-c", "alias.wait=!sleep 5", "wait"], temp_dir.path());
+
+        let result = git::run_with_timeout(command, std::time::Duration::from_millis(100)).await;
+
+        assert!(matches!(result, Err(GitCommandError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn run_git_command_with_options_retries_on_lock_contention() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        std::fs::write(repo_path.join("file.txt"), "contents").unwrap();
+        let lock = repo_path.join(".git").join("index.lock");
+        std::fs::write(&lock, "").unwrap();
+
+        let no_retry = run_git_command_with_options(
+            &["add", "file.txt"],
+            repo_path,
+            &GitCommandOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!no_retry.status.success());
+
+        let release = {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                std::fs::remove_file(lock).unwrap();
+            })
+        };
+        let retried = run_git_command_with_options(
+            &["add", "file.txt"],
+            repo_path,
+            &GitCommandOptions {
+                retries: 5,
+                backoff: std::time::Duration::from_millis(100),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        release.join().unwrap();
+
+        assert!(retried.status.success());
+    }
+
+    #[tokio::test]
+    async fn run_git_command_with_options_uses_options_env() {
+        let temp_dir = tempdir().unwrap();
+        let options = GitCommandOptions {
+            env: Some(GitCommandEnv::new(HashMap::from([
+                (
+                    "GIT_AUTHOR_NAME".to_string(),
+                    "Configured Author".to_string(),
+                ),
+                (
+                    "GIT_AUTHOR_EMAIL".to_string(),
+                    "author@example.com".to_string(),
+                ),
+            ]))),
+            ..Default::default()
+        };
+
+        let output =
+            run_git_command_with_options(&["var", "GIT_AUTHOR_IDENT"], temp_dir.path(), &options)
+                .await
+                .unwrap();
+
+        assert!(output.status.success());
+        assert!(
+            String::from_utf8_lossy(&output.stdout)
+                .starts_with("Configured Author <author@example.com>")
+        );
+    }
+
     #[tokio::test]
     async fn collect_git_info_git_repository() {
         let temp_dir = tempdir().unwrap();
@@ -165,6 +585,83 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn collect_git_info_falls_back_to_first_remote() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        configure_git_identity(repo_path);
+        commit_as(repo_path, "Alice", "alice@example.com", "a.txt");
+        git(
+            repo_path,
+            &["remote", "add", "github", "https://example.com/github.git"],
+        );
+
+        let nested = repo_path.join("nested").join("deeper");
+        std::fs::create_dir_all(&nested).unwrap();
+        let backend = git_backend(repo_path.to_path_buf());
+
+        let git_info = collect_git_info(&backend, &nested)
+            .await
+            .expect("git info should be collected");
+
+        assert_eq!(
+            git_info.repository_url.as_deref(),
+            Some("https://example.com/github.git"),
+        );
+    }
+
+    #[tokio::test]
+    async fn collect_git_info_reports_submodules() {
+        let temp_dir = tempdir().unwrap();
+        let library = temp_dir.path().join("library");
+        let superproject = temp_dir.path().join("superproject");
+        for repo in [&library, &superproject] {
+            std::fs::create_dir(repo).unwrap();
+            git(repo, &["init", "--initial-branch", "main"]);
+            configure_git_identity(repo);
+            commit_as(repo, "Alice", "alice@example.com", "README.md");
+        }
+        git(
+            &superproject,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                library.to_str().unwrap(),
+                "vendor/library",
+            ],
+        );
+        let backend = git_backend(superproject.clone());
+        let submodule = superproject.join("vendor").join("library");
+        let recorded = git(&submodule, &["rev-parse", "HEAD"]);
+
+        let git_info = collect_git_info(&backend, &superproject).await.unwrap();
+        assert_eq!(
+            git_info.submodules,
+            vec![SubmoduleStatus {
+                path: "vendor/library".to_string(),
+                sha: recorded,
+                state: SubmoduleState::InSync,
+            }]
+        );
+
+        commit_as(&submodule, "Bob", "bob@example.com", "CHANGES.md");
+        let moved = git(&submodule, &["rev-parse", "HEAD"]);
+
+        let git_info = collect_git_info(&backend, &superproject).await.unwrap();
+        assert_eq!(
+            git_info.submodules,
+            vec![SubmoduleStatus {
+                path: "vendor/library".to_string(),
+                sha: moved,
+                state: SubmoduleState::Modified,
+            }]
+        );
+    }
+
     #[tokio::test]
     async fn collect_git_info_detached_head() {
         let temp_dir = tempdir().unwrap();
@@ -257,59 +754,1267 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn collect_git_info_returns_none_for_non_git_backend() {
-        struct DummyBackend;
+    async fn collect_git_info_reports_exact_tag() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
 
-        impl RevisionControlSystem for DummyBackend {
-            fn kind(&self) -> RevisionControlKind {
-                RevisionControlKind::Darcs
-            }
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["init", "--initial-branch", "main"])
+            .output()
+            .unwrap();
 
-            fn root(&self) -> &Path {
-                Path::new("/")
-            }
+        configure_git_identity(repo_path);
 
-            fn capabilities(&self) -> crate::revision_control::RevisionControlCapabilities {
-                crate::revision_control::RevisionControlCapabilities::new(false, false)
-            }
-        }
+        std::fs::write(repo_path.join("README.md"), "# Test Repo").unwrap();
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["add", "README.md"])
+            .output()
+            .unwrap();
 
-        let backend = DummyBackend;
-        let result = collect_git_info(&backend, Path::new("/tmp")).await;
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["commit", "-m", "Initial commit"])
+            .output()
+            .unwrap();
 
-        assert!(result.is_none());
-    }
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["tag", "v1.2.3"])
+            .output()
+            .unwrap();
 
-    #[test]
-    fn git_info_serialization_includes_fields() {
-        let info = GitInfo {
-            commit_hash: Some("abc123def456".to_string()),
-            branch: Some("main".to_string()),
-            repository_url: Some("https://example.com/repo.git".to_string()),
-        };
+        let backend = git_backend(repo_path.to_path_buf());
 
-        let json = serde_json::to_string(&info).expect("serialization should succeed");
-        let parsed: Value = serde_json::from_str(&json).expect("json should parse");
+        let git_info = collect_git_info(&backend, repo_path)
+            .await
+            .expect("git info should be collected");
 
-        assert_eq!(parsed["commit_hash"], "abc123def456");
-        assert_eq!(parsed["branch"], "main");
-        assert_eq!(parsed["repository_url"], "https://example.com/repo.git");
+        assert_eq!(git_info.tag.as_deref(), Some("v1.2.3"));
     }
 
-    #[test]
-    fn git_info_serialization_skips_nones() {
-        let info = GitInfo {
-            commit_hash: None,
-            branch: None,
-            repository_url: None,
-        };
+    #[tokio::test]
+    async fn collect_git_info_untagged_commit_has_no_tag() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
 
-        let json = serde_json::to_string(&info).expect("serialization should succeed");
-        let parsed: Value = serde_json::from_str(&json).expect("json should parse");
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["init", "--initial-branch", "main"])
+            .output()
+            .unwrap();
 
-        let object = parsed.as_object().expect("expected json object");
-        assert!(!object.contains_key("commit_hash"));
-        assert!(!object.contains_key("branch"));
-        assert!(!object.contains_key("repository_url"));
+        configure_git_identity(repo_path);
+
+        std::fs::write(repo_path.join("README.md"), "# Test Repo").unwrap();
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["add", "README.md"])
+            .output()
+            .unwrap();
+
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["commit", "-m", "Initial commit"])
+            .output()
+            .unwrap();
+
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["tag", "v1.2.3"])
+            .output()
+            .unwrap();
+
+        std::fs::write(repo_path.join("README.md"), "# Updated").unwrap();
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["commit", "-am", "Second commit"])
+            .output()
+            .unwrap();
+
+        let backend = git_backend(repo_path.to_path_buf());
+
+        let git_info = collect_git_info(&backend, repo_path)
+            .await
+            .expect("git info should be collected");
+
+        assert!(git_info.tag.is_none());
+    }
+
+    #[tokio::test]
+    async fn contributor_count_counts_distinct_authors() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["init", "--initial-branch", "main"])
+            .output()
+            .unwrap();
+
+        commit_as(repo_path, "Alice", "alice@example.com", "a.txt");
+        commit_as(repo_path, "Bob", "bob@example.com", "b.txt");
+        commit_as(repo_path, "Alice", "alice@example.com", "c.txt");
+
+        assert_eq!(contributor_count(repo_path).await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn contributor_count_respects_mailmap() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["init", "--initial-branch", "main"])
+            .output()
+            .unwrap();
+
+        commit_as(repo_path, "Alice", "alice@example.com", "a.txt");
+        commit_as(repo_path, "Alice Work", "alice@work.example.com", "b.txt");
+        std::fs::write(
+            repo_path.join(".mailmap"),
+            "Alice <alice@example.com> Alice Work <alice@work.example.com>\n",
+        )
+        .unwrap();
+
+        assert_eq!(contributor_count(repo_path).await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn author_stats_aggregates_per_author() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+
+        commit_as(repo_path, "Alice", "alice@example.com", "a.txt");
+        commit_as(repo_path, "Alice", "alice@example.com", "c.txt");
+        // Replaces the single line Alice wrote in a.txt.
+        commit_as(repo_path, "Bob", "bob@example.com", "a.txt");
+
+        assert_eq!(
+            author_stats(repo_path, None).await,
+            vec![
+                AuthorStat {
+                    name: "Alice".to_string(),
+                    email: "alice@example.com".to_string(),
+                    commits: 2,
+                    insertions: 2,
+                    deletions: 0,
+                },
+                AuthorStat {
+                    name: "Bob".to_string(),
+                    email: "bob@example.com".to_string(),
+                    commits: 1,
+                    insertions: 1,
+                    deletions: 1,
+                },
+            ]
+        );
+        assert!(
+            author_stats(repo_path, Some("2100-01-01".to_string()))
+                .await
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn subtree_hash_tracks_working_tree_changes() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        std::fs::create_dir(repo_path.join("sub")).unwrap();
+        commit_as(repo_path, "Alice", "alice@example.com", "sub/a.txt");
+        commit_as(repo_path, "Alice", "alice@example.com", "outside.txt");
+
+        let sub = Path::new("sub");
+        let committed = subtree_hash(repo_path, sub).await.expect("subtree hash");
+        assert_eq!(committed, git(repo_path, &["rev-parse", "HEAD:sub"]));
+
+        std::fs::write(repo_path.join("outside.txt"), "changed").unwrap();
+        assert_eq!(subtree_hash(repo_path, sub).await, Some(committed.clone()));
+
+        std::fs::write(repo_path.join("sub").join("a.txt"), "changed").unwrap();
+        let modified = subtree_hash(repo_path, sub).await.expect("subtree hash");
+        assert_ne!(modified, committed);
+        assert_eq!(subtree_hash(repo_path, sub).await, Some(modified));
+
+        let nested = subtree_hash(&repo_path.join("sub"), Path::new(".")).await;
+        assert_eq!(nested, subtree_hash(repo_path, sub).await);
+        assert_eq!(
+            git(repo_path, &["status", "--porcelain"]).lines().count(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn rebase_progress_reads_state_files() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+
+        assert_eq!(rebase_progress(repo_path).await, None);
+        assert_eq!(git_operation_state(repo_path).await, None);
+
+        let rebase_merge = repo_path.join(".git").join("rebase-merge");
+        std::fs::create_dir(&rebase_merge).unwrap();
+        std::fs::write(rebase_merge.join("msgnum"), "3\n").unwrap();
+        std::fs::write(rebase_merge.join("end"), "7\n").unwrap();
+
+        let progress = RebaseProgress {
+            current: 3,
+            total: 7,
+        };
+        assert_eq!(rebase_progress(repo_path).await, Some(progress));
+        assert_eq!(
+            git_operation_state(repo_path).await,
+            Some(GitOperationState::Rebase {
+                progress: Some(progress),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn recent_commits_page_returns_window() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        for file in ["1.txt", "2.txt", "3.txt", "4.txt", "5.txt"] {
+            commit_as(repo_path, "Alice", "alice@example.com", file);
+        }
+
+        let subjects: Vec<String> = recent_commits_page(repo_path, 2, 2)
+            .await
+            .into_iter()
+            .map(|commit| commit.subject)
+            .collect();
+
+        assert_eq!(subjects, vec!["3.txt", "2.txt"]);
+    }
+
+    #[tokio::test]
+    async fn recent_commits_from_pages_with_cursor() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        for file in ["1.txt", "2.txt", "3.txt"] {
+            commit_as(repo_path, "Alice", "alice@example.com", file);
+        }
+        let subjects = |entries: &[CommitLogEntry]| -> Vec<String> {
+            entries.iter().map(|entry| entry.subject.clone()).collect()
+        };
+
+        let (first, cursor) = recent_commits_from(repo_path, "HEAD", 2).await;
+        assert_eq!(subjects(&first), vec!["3.txt", "2.txt"]);
+        let cursor = cursor.expect("more history remains");
+        assert_eq!(cursor, git(repo_path, &["rev-parse", "HEAD~2"]));
+
+        let (second, cursor) = recent_commits_from(repo_path, &cursor, 2).await;
+        assert_eq!(subjects(&second), vec!["1.txt"]);
+        assert_eq!(cursor, None);
+
+        let (missing, cursor) = recent_commits_from(repo_path, "no-such-ref", 2).await;
+        assert!(missing.is_empty());
+        assert_eq!(cursor, None);
+    }
+
+    #[tokio::test]
+    async fn recent_commits_include_multi_line_bodies() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "1.txt");
+        std::fs::write(repo_path.join("2.txt"), "2.txt").unwrap();
+        git(repo_path, &["add", "2.txt"]);
+        git(
+            repo_path,
+            &[
+                "-c",
+                "user.name=Alice",
+                "-c",
+                "user.email=alice@example.com",
+                "commit",
+                "-m",
+                "Release 2.0",
+                "-m",
+                "- faster diffs\n- new picker",
+                "-m",
+                "Thanks to everyone who tested.",
+            ],
+        );
+
+        let commits = recent_commits(repo_path, 10).await;
+
+        let summary: Vec<(&str, &str)> = commits
+            .iter()
+            .map(|commit| (commit.subject.as_str(), commit.body.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "Release 2.0",
+                    "- faster diffs\n- new picker\n\nThanks to everyone who tested."
+                ),
+                ("1.txt", ""),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn recent_commits_with_notes_reads_requested_ref() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "1.txt");
+        commit_as(repo_path, "Alice", "alice@example.com", "2.txt");
+        git(repo_path, &["config", "user.name", "Bob"]);
+        git(repo_path, &["config", "user.email", "bob@example.com"]);
+        git(repo_path, &["notes", "add", "-m", "default note", "HEAD~1"]);
+        git(
+            repo_path,
+            &[
+                "notes",
+                "--ref=review",
+                "add",
+                "-m",
+                "approved\n\nby bob",
+                "HEAD",
+            ],
+        );
+        let notes = |entries: Vec<CommitLogEntry>| -> Vec<Option<String>> {
+            entries.into_iter().map(|entry| entry.notes).collect()
+        };
+
+        assert_eq!(
+            notes(recent_commits_with_notes(repo_path, 10, None).await),
+            vec![None, Some("default note".to_string())]
+        );
+        assert_eq!(
+            notes(recent_commits_with_notes(repo_path, 10, Some("review")).await),
+            vec![Some("approved\n\nby bob".to_string()), None]
+        );
+        assert_eq!(notes(recent_commits(repo_path, 10).await), vec![None, None]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn has_hooks_ignores_samples_and_non_executables() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        let hooks = repo_path.join(".git/hooks");
+        std::fs::create_dir_all(&hooks).unwrap();
+        let write_hook = |name: &str, mode: u32| {
+            let path = hooks.join(name);
+            std::fs::write(&path, "#!/bin/sh\nexit 0\n").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        };
+
+        write_hook("pre-commit.sample", 0o755);
+        write_hook("post-commit", 0o644);
+        assert!(!has_hooks(repo_path).await);
+
+        write_hook("pre-commit", 0o755);
+        assert!(has_hooks(repo_path).await);
+
+        let custom = repo_path.join("custom-hooks");
+        std::fs::create_dir(&custom).unwrap();
+        git(repo_path, &["config", "core.hooksPath", "custom-hooks"]);
+        assert!(!has_hooks(repo_path).await);
+
+        assert!(!has_hooks(&temp_dir.path().join("missing")).await);
+    }
+
+    #[tokio::test]
+    async fn blame_reports_committed_and_uncommitted_lines() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        std::fs::write(repo_path.join("lines.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+        git(repo_path, &["add", "lines.txt"]);
+        git(
+            repo_path,
+            &[
+                "-c",
+                "user.name=Alice",
+                "-c",
+                "user.email=alice@example.com",
+                "commit",
+                "-m",
+                "lines",
+            ],
+        );
+        let head = git(repo_path, &["rev-parse", "HEAD"]);
+        let committed_at: i64 = git(repo_path, &["log", "-1", "--format=%at"])
+            .parse()
+            .unwrap();
+        std::fs::write(repo_path.join("lines.txt"), "one\nTWO\nthree\nfour\n").unwrap();
+
+        let lines = blame(repo_path, Path::new("lines.txt"), 1..=3)
+            .await
+            .unwrap();
+
+        let summary: Vec<(BlameCommit, usize, &str)> = lines
+            .iter()
+            .map(|line| (line.sha.clone(), line.line_number, line.line.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (BlameCommit::Committed(head.clone()), 1, "one"),
+                (BlameCommit::Uncommitted, 2, "TWO"),
+                (BlameCommit::Committed(head), 3, "three"),
+            ]
+        );
+        assert_eq!(lines[0].author, "Alice");
+        assert_eq!(lines[0].timestamp, committed_at);
+        assert_eq!(lines[2].author, "Alice");
+
+        assert!(
+            blame(repo_path, Path::new("missing.txt"), 1..=1)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn path_status_classifies_paths() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        std::fs::write(repo_path.join(".gitignore"), "*.log\n").unwrap();
+        commit_as(repo_path, "Alice", "alice@example.com", "tracked.txt");
+        std::fs::write(repo_path.join("debug.log"), "noise").unwrap();
+        std::fs::write(repo_path.join("new.txt"), "new").unwrap();
+
+        let status = |path: &'static str| path_status(repo_path, Path::new(path));
+        assert_eq!(status("tracked.txt").await, Some(PathStatus::Tracked));
+        assert_eq!(status("debug.log").await, Some(PathStatus::Ignored));
+        assert_eq!(status("new.txt").await, Some(PathStatus::Untracked));
+        assert_eq!(status("missing.txt").await, Some(PathStatus::Missing));
+
+        let not_repo = tempdir().unwrap();
+        assert_eq!(
+            path_status(not_repo.path(), Path::new("file.txt")).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn list_worktrees_reports_branch_head_and_lock() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let repo_path = root.join("repo");
+        std::fs::create_dir(&repo_path).unwrap();
+        git(&repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(&repo_path, "Alice", "alice@example.com", "1.txt");
+        let head = git(&repo_path, &["rev-parse", "HEAD"]);
+        let feature = root.join("feature");
+        let detached = root.join("detached");
+        git(
+            &repo_path,
+            &[
+                "worktree",
+                "add",
+                "-b",
+                "feature",
+                feature.to_str().unwrap(),
+            ],
+        );
+        git(
+            &repo_path,
+            &["worktree", "add", "--detach", detached.to_str().unwrap()],
+        );
+        git(
+            &repo_path,
+            &["worktree", "lock", detached.to_str().unwrap()],
+        );
+
+        let worktree = |path: &Path, branch: Option<&str>, is_locked| WorktreeInfo {
+            path: path.to_path_buf(),
+            head_sha: Some(head.clone()),
+            branch: branch.map(str::to_string),
+            is_locked,
+        };
+        assert_eq!(
+            list_worktrees(&feature).await,
+            vec![
+                worktree(&repo_path, Some("main"), false),
+                worktree(&detached, None, true),
+                worktree(&feature, Some("feature"), false),
+            ]
+        );
+        assert!(list_worktrees(temp_dir.path()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn branches_with_tracking_reports_divergence() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "1.txt");
+        git(repo_path, &["branch", "feature"]);
+        git(repo_path, &["branch", "stale"]);
+        git(repo_path, &["checkout", "feature"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "feature-1.txt");
+        commit_as(repo_path, "Alice", "alice@example.com", "feature-2.txt");
+        git(repo_path, &["checkout", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "2.txt");
+        git(repo_path, &["branch", "--set-upstream-to=main", "feature"]);
+        git(repo_path, &["branch", "--set-upstream-to=main", "stale"]);
+
+        let tracking = |name: &str, upstream: Option<&str>, ahead, behind| BranchTracking {
+            name: name.to_string(),
+            upstream: upstream.map(str::to_string),
+            ahead,
+            behind,
+        };
+        assert_eq!(
+            branches_with_tracking(repo_path).await,
+            vec![
+                tracking("feature", Some("main"), 2, 1),
+                tracking("main", None, 0, 0),
+                tracking("stale", Some("main"), 0, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn pending_operation_detects_marker_files() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "1.txt");
+        let head = git(repo_path, &["rev-parse", "HEAD"]);
+        let git_dir = repo_path.join(".git");
+        assert_eq!(pending_operation(repo_path), None);
+
+        std::fs::write(git_dir.join("MERGE_HEAD"), &head).unwrap();
+        assert_eq!(pending_operation(repo_path), Some(PendingOperation::Merge));
+        std::fs::remove_file(git_dir.join("MERGE_HEAD")).unwrap();
+
+        std::fs::write(git_dir.join("CHERRY_PICK_HEAD"), &head).unwrap();
+        assert_eq!(
+            pending_operation(repo_path),
+            Some(PendingOperation::CherryPick)
+        );
+        std::fs::remove_file(git_dir.join("CHERRY_PICK_HEAD")).unwrap();
+
+        for dir in ["rebase-merge", "rebase-apply"] {
+            std::fs::create_dir(git_dir.join(dir)).unwrap();
+            assert_eq!(pending_operation(repo_path), Some(PendingOperation::Rebase));
+            std::fs::remove_dir(git_dir.join(dir)).unwrap();
+        }
+
+        // A revert is reported by `git_operation_state` but does not block snapshots.
+        std::fs::write(git_dir.join("REVERT_HEAD"), &head).unwrap();
+        assert_eq!(pending_operation(repo_path), None);
+        std::fs::remove_file(git_dir.join("REVERT_HEAD")).unwrap();
+        assert_eq!(pending_operation(repo_path), None);
+    }
+
+    #[tokio::test]
+    async fn recent_commits_filtered_by_author_and_path() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+
+        std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(["init", "--initial-branch", "main"])
+            .output()
+            .unwrap();
+        std::fs::create_dir(repo_path.join("src")).unwrap();
+
+        commit_as(repo_path, "Alice", "alice@example.com", "src/a.txt");
+        commit_as(repo_path, "Bob", "bob@example.com", "src/b.txt");
+        commit_as(repo_path, "Alice", "alice@example.com", "notes.txt");
+
+        let subjects = |commits: Vec<CommitLogEntry>| -> Vec<String> {
+            commits.into_iter().map(|commit| commit.subject).collect()
+        };
+
+        let by_alice = recent_commits_filtered(
+            repo_path,
+            10,
+            &RecentCommitsFilter {
+                author: Some("Alice".to_string()),
+                paths: Vec::new(),
+            },
+        )
+        .await;
+        assert_eq!(subjects(by_alice), vec!["notes.txt", "src/a.txt"]);
+
+        let in_src = recent_commits_filtered(
+            repo_path,
+            10,
+            &RecentCommitsFilter {
+                author: None,
+                paths: vec![PathBuf::from("src")],
+            },
+        )
+        .await;
+        assert_eq!(subjects(in_src), vec!["src/b.txt", "src/a.txt"]);
+
+        let alice_in_src = recent_commits_filtered(
+            repo_path,
+            10,
+            &RecentCommitsFilter {
+                author: Some("Alice".to_string()),
+                paths: vec![PathBuf::from("src")],
+            },
+        )
+        .await;
+        assert_eq!(subjects(alice_in_src), vec!["src/a.txt"]);
+    }
+
+    fn git(repo_path: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .current_dir(repo_path)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    #[tokio::test]
+    async fn git_diff_to_remote_for_prefers_named_remote() {
+        let temp_dir = tempdir().unwrap();
+        let origin = temp_dir.path().join("origin.git");
+        let upstream = temp_dir.path().join("upstream.git");
+        let repo_path = temp_dir.path().join("repo");
+        for bare in [&origin, &upstream] {
+            git(temp_dir.path(), &["init", "--bare", bare.to_str().unwrap()]);
+        }
+        std::fs::create_dir(&repo_path).unwrap();
+        git(&repo_path, &["init", "--initial-branch", "main"]);
+        git(
+            &repo_path,
+            &["remote", "add", "origin", origin.to_str().unwrap()],
+        );
+        git(
+            &repo_path,
+            &["remote", "add", "upstream", upstream.to_str().unwrap()],
+        );
+
+        commit_as(&repo_path, "Alice", "alice@example.com", "first.txt");
+        let origin_sha = git(&repo_path, &["rev-parse", "HEAD"]);
+        git(&repo_path, &["push", "origin", "main"]);
+        commit_as(&repo_path, "Alice", "alice@example.com", "second.txt");
+        let upstream_sha = git(&repo_path, &["rev-parse", "HEAD"]);
+        git(&repo_path, &["push", "upstream", "main"]);
+        commit_as(&repo_path, "Alice", "alice@example.com", "local.txt");
+
+        let default = git_diff_to_remote(&repo_path).await.unwrap();
+        assert_eq!(default.sha.0, origin_sha);
+
+        let preferred = git_diff_to_remote_for(&repo_path, "upstream")
+            .await
+            .unwrap();
+        assert_eq!(preferred.sha.0, upstream_sha);
+
+        let missing = git_diff_to_remote_for(&repo_path, "missing").await.unwrap();
+        assert_eq!(missing.sha.0, origin_sha);
+    }
+
+    #[tokio::test]
+    async fn git_diff_to_remote_with_caps_large_untracked_files() {
+        let temp_dir = tempdir().unwrap();
+        let origin = temp_dir.path().join("origin.git");
+        let repo_path = temp_dir.path().join("repo");
+        git(
+            temp_dir.path(),
+            &["init", "--bare", origin.to_str().unwrap()],
+        );
+        std::fs::create_dir(&repo_path).unwrap();
+        git(&repo_path, &["init", "--initial-branch", "main"]);
+        git(
+            &repo_path,
+            &["remote", "add", "origin", origin.to_str().unwrap()],
+        );
+        commit_as(&repo_path, "Alice", "alice@example.com", "first.txt");
+        git(&repo_path, &["push", "origin", "main"]);
+
+        let large = "large content\n".repeat(1024);
+        std::fs::write(repo_path.join("large.bin"), &large).unwrap();
+        std::fs::write(repo_path.join("small.txt"), "small content\n").unwrap();
+
+        let uncapped = git_diff_to_remote(&repo_path).await.unwrap();
+        assert!(uncapped.diff.contains("+large content"));
+
+        let capped = git_diff_to_remote_with(
+            &repo_path,
+            &GitDiffToRemoteOptions {
+                max_untracked_file_size: Some(1024),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!capped.diff.contains("large content"));
+        assert!(
+            capped
+                .diff
+                .contains(&omitted_file_placeholder("large.bin", large.len() as u64))
+        );
+        assert!(capped.diff.contains("+small content"));
+    }
+
+    #[tokio::test]
+    async fn changed_files_content_returns_truncated_contents() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "small.txt");
+        commit_as(repo_path, "Alice", "alice@example.com", "unchanged.txt");
+
+        std::fs::write(repo_path.join("small.txt"), "small change\n").unwrap();
+        std::fs::write(repo_path.join("large.txt"), "x".repeat(64)).unwrap();
+        std::fs::write(repo_path.join("binary.bin"), [0u8, 1, 2, 3]).unwrap();
+
+        let contents = changed_files_content(repo_path, 16).await;
+
+        assert_eq!(
+            contents,
+            vec![
+                (PathBuf::from("large.txt"), "x".repeat(16)),
+                (PathBuf::from("small.txt"), "small change\n".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn remote_branches_lists_remote_tracking_branches() {
+        let temp_dir = tempdir().unwrap();
+        let origin = temp_dir.path().join("origin");
+        let clone = temp_dir.path().join("clone");
+        std::fs::create_dir(&origin).unwrap();
+        git(&origin, &["init", "--initial-branch", "main"]);
+        commit_as(&origin, "Alice", "alice@example.com", "first.txt");
+        git(&origin, &["branch", "feature"]);
+        git(
+            temp_dir.path(),
+            &["clone", origin.to_str().unwrap(), clone.to_str().unwrap()],
+        );
+
+        let branches = remote_branches(&clone).await;
+
+        assert_eq!(branches, vec!["origin/feature", "origin/main"]);
+        assert!(remote_branches(&origin).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn is_diverged_from_upstream_compares_with_tracking_branch() {
+        let temp_dir = tempdir().unwrap();
+        let origin = temp_dir.path().join("origin");
+        let clone = temp_dir.path().join("clone");
+        std::fs::create_dir(&origin).unwrap();
+        git(&origin, &["init", "--initial-branch", "main"]);
+        commit_as(&origin, "Alice", "alice@example.com", "first.txt");
+        git(
+            temp_dir.path(),
+            &["clone", origin.to_str().unwrap(), clone.to_str().unwrap()],
+        );
+        assert_eq!(is_diverged_from_upstream(&origin).await, None);
+
+        commit_as(&origin, "Alice", "alice@example.com", "remote.txt");
+        git(&clone, &["fetch", "origin"]);
+        assert_eq!(is_diverged_from_upstream(&clone).await, Some(false));
+
+        commit_as(&clone, "Bob", "bob@example.com", "local.txt");
+        assert_eq!(is_diverged_from_upstream(&clone).await, Some(true));
+    }
+
+    #[tokio::test]
+    async fn is_fork_compares_origin_and_upstream_urls() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+        assert_eq!(is_fork(repo).await, None);
+
+        git(repo, &["init", "--initial-branch", "main"]);
+        git(
+            repo,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://github.com/alice/project.git",
+            ],
+        );
+        assert_eq!(is_fork(repo).await, Some(false));
+
+        git(
+            repo,
+            &["remote", "add", "upstream", "git@github.com:Alice/project"],
+        );
+        assert_eq!(is_fork(repo).await, Some(false));
+
+        git(
+            repo,
+            &[
+                "remote",
+                "set-url",
+                "upstream",
+                "ssh://git@github.com/acme/project.git",
+            ],
+        );
+        assert_eq!(is_fork(repo).await, Some(true));
+    }
+
+    #[tokio::test]
+    async fn branch_ancestry_caps_branches_per_remote() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+        git(repo, &["init", "--initial-branch", "main"]);
+        commit_as(repo, "Alice", "alice@example.com", "first.txt");
+        let missing_remote = temp_dir.path().join("missing");
+        git(
+            repo,
+            &["remote", "add", "origin", missing_remote.to_str().unwrap()],
+        );
+        for index in 0..25 {
+            git(
+                repo,
+                &[
+                    "update-ref",
+                    &format!("refs/remotes/origin/b{index:02}"),
+                    "HEAD",
+                ],
+            );
+        }
+
+        let ancestry = git::branch_ancestry(repo).await.unwrap();
+
+        let mut expected = vec!["main".to_string()];
+        expected.extend((0..20).map(|index| format!("b{index:02}")));
+        assert_eq!(ancestry, expected);
+    }
+
+    #[tokio::test]
+    async fn default_branch_prefers_remote_head() {
+        let temp_dir = tempdir().unwrap();
+        let origin = temp_dir.path().join("origin");
+        let clone = temp_dir.path().join("clone");
+        std::fs::create_dir(&origin).unwrap();
+        git(&origin, &["init", "--initial-branch", "trunk"]);
+        commit_as(&origin, "Alice", "alice@example.com", "first.txt");
+        assert_eq!(default_branch(&origin).await, None);
+
+        git(&origin, &["branch", "main"]);
+        assert_eq!(default_branch(&origin).await, Some("main".to_string()));
+
+        git(
+            temp_dir.path(),
+            &["clone", origin.to_str().unwrap(), clone.to_str().unwrap()],
+        );
+        git(&clone, &["checkout", "-b", "feature"]);
+        assert_eq!(default_branch(&clone).await, Some("trunk".to_string()));
+    }
+
+    #[tokio::test]
+    async fn default_branch_reads_origin_head_file_without_remotes() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+        git(repo, &["init", "--initial-branch", "trunk"]);
+        commit_as(repo, "Alice", "alice@example.com", "first.txt");
+
+        let origin_refs = repo.join(".git/refs/remotes/origin");
+        std::fs::create_dir_all(&origin_refs).unwrap();
+        std::fs::write(
+            origin_refs.join("HEAD"),
+            "ref: refs/remotes/origin/release/2.x\n",
+        )
+        .unwrap();
+
+        assert_eq!(git(repo, &["remote"]), "");
+        assert_eq!(default_branch(repo).await, Some("release/2.x".to_string()));
+    }
+
+    #[tokio::test]
+    async fn collect_git_info_abbreviates_hash_like_git() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "first.txt");
+        git(repo_path, &["config", "core.abbrev", "12"]);
+
+        let git_info = collect_git_info(&git_backend(repo_path.to_path_buf()), repo_path)
+            .await
+            .expect("git info should be collected");
+
+        let full = git_info.commit_hash.expect("commit hash");
+        let short = git_info.short_commit_hash.expect("short commit hash");
+        assert_eq!(short, git(repo_path, &["rev-parse", "--short", "HEAD"]));
+        assert_eq!(short.len(), 12);
+        assert!(full.starts_with(&short));
+    }
+
+    #[tokio::test]
+    async fn shallow_clones_are_reported() {
+        let temp_dir = tempdir().unwrap();
+        let origin = temp_dir.path().join("origin");
+        let clone = temp_dir.path().join("clone");
+        std::fs::create_dir(&origin).unwrap();
+        git(&origin, &["init", "--initial-branch", "main"]);
+        commit_as(&origin, "Alice", "alice@example.com", "first.txt");
+        commit_as(&origin, "Alice", "alice@example.com", "second.txt");
+        let origin_url = format!("file://{}", origin.display());
+        git(
+            temp_dir.path(),
+            &[
+                "clone",
+                "--depth",
+                "1",
+                &origin_url,
+                clone.to_str().unwrap(),
+            ],
+        );
+
+        let origin_info = collect_git_info(&git_backend(origin.clone()), &origin)
+            .await
+            .expect("git info should be collected");
+        assert!(!origin_info.is_shallow);
+        let clone_info = collect_git_info(&git_backend(clone.clone()), &clone)
+            .await
+            .expect("git info should be collected");
+        assert!(clone_info.is_shallow);
+
+        // Without its remote-tracking branch, the truncated history reaches no remote commit.
+        git(&clone, &["update-ref", "-d", "refs/remotes/origin/main"]);
+        let options = GitDiffToRemoteOptions::default();
+        assert_eq!(
+            git_diff_to_remote_checked(&clone, &options).await.err(),
+            Some(GitDiffToRemoteError::ShallowHistory)
+        );
+        assert_eq!(
+            git_diff_to_remote_checked(&origin, &options).await.err(),
+            Some(GitDiffToRemoteError::Unavailable)
+        );
+    }
+
+    #[tokio::test]
+    async fn collect_git_info_counts_ignored_paths_on_request() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        std::fs::write(repo_path.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        commit_as(repo_path, "Alice", "alice@example.com", "tracked.txt");
+        std::fs::write(repo_path.join("debug.log"), "noise").unwrap();
+        std::fs::create_dir(repo_path.join("build")).unwrap();
+        std::fs::write(repo_path.join("build/a.o"), "a").unwrap();
+        std::fs::write(repo_path.join("build/b.o"), "b").unwrap();
+        let backend = git_backend(repo_path.to_path_buf());
+
+        let default_info = collect_git_info(&backend, repo_path).await.unwrap();
+        assert_eq!(default_info.ignored_count, None);
+
+        let info = collect_git_info_with(
+            &backend,
+            repo_path,
+            &GitInfoOptions {
+                count_ignored: true,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(info.ignored_count, Some(2));
+    }
+
+    #[tokio::test]
+    async fn blocking_git_info_matches_async() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "first.txt");
+        git(repo_path, &["tag", "v1.0.0"]);
+        git(
+            repo_path,
+            &["remote", "add", "fork", "https://example.com/fork.git"],
+        );
+        let backend = git_backend(repo_path.to_path_buf());
+
+        let async_info = collect_git_info(&backend, repo_path).await;
+        let blocking_info = collect_git_info_blocking(&backend, repo_path);
+
+        let blocking_info = blocking_info.expect("git info should be collected");
+        let async_info = async_info.expect("git info should be collected");
+        assert_eq!(
+            serde_json::to_value(&blocking_info).unwrap(),
+            serde_json::to_value(&async_info).unwrap()
+        );
+        assert_eq!(blocking_info.branch.as_deref(), Some("main"));
+        assert_eq!(blocking_info.tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(
+            blocking_info.repository_url.as_deref(),
+            Some("https://example.com/fork.git")
+        );
+        assert!(collect_git_info_blocking(&backend, &temp_dir.path().join("missing")).is_none());
+    }
+
+    #[tokio::test]
+    async fn collect_git_info_reports_upstream() {
+        let temp_dir = tempdir().unwrap();
+        let origin = temp_dir.path().join("origin");
+        let clone = temp_dir.path().join("clone");
+        std::fs::create_dir(&origin).unwrap();
+        git(&origin, &["init", "--initial-branch", "main"]);
+        commit_as(&origin, "Alice", "alice@example.com", "first.txt");
+        git(
+            temp_dir.path(),
+            &["clone", origin.to_str().unwrap(), clone.to_str().unwrap()],
+        );
+
+        let tracking = collect_git_info(&git_backend(clone.clone()), &clone)
+            .await
+            .expect("git info should be collected");
+        assert_eq!(tracking.upstream.as_deref(), Some("origin/main"));
+
+        git(&clone, &["checkout", "-b", "local-only"]);
+        let untracked = collect_git_info(&git_backend(clone.clone()), &clone)
+            .await
+            .expect("git info should be collected");
+        assert_eq!(untracked.branch.as_deref(), Some("local-only"));
+        assert_eq!(untracked.upstream, None);
+    }
+
+    #[tokio::test]
+    async fn collect_git_info_returns_none_for_non_git_backend() {
+        struct DummyBackend;
+
+        impl RevisionControlSystem for DummyBackend {
+            fn kind(&self) -> RevisionControlKind {
+                RevisionControlKind::Darcs
+            }
+
+            fn root(&self) -> &Path {
+                Path::new("/")
+            }
+
+            fn capabilities(&self) -> crate::revision_control::RevisionControlCapabilities {
+                crate::revision_control::RevisionControlCapabilities::new(false, false)
+            }
+        }
+
+        let backend = DummyBackend;
+        let result = collect_git_info(&backend, Path::new("/tmp")).await;
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn git_info_serialization_includes_fields() {
+        let info = GitInfo {
+            commit_hash: Some("abc123def456".to_string()),
+            short_commit_hash: Some("abc123d".to_string()),
+            branch: Some("main".to_string()),
+            upstream: Some("origin/main".to_string()),
+            repository_url: Some("https://example.com/repo.git".to_string()),
+            parsed_remote: None,
+            tag: Some("v1.0.0".to_string()),
+            uses_lfs: true,
+            is_shallow: true,
+            ignored_count: None,
+            submodules: vec![SubmoduleStatus {
+                path: "vendor/lib".to_string(),
+                sha: "0123456789abcdef".to_string(),
+                state: SubmoduleState::Modified,
+            }],
+        };
+
+        let json = serde_json::to_string(&info).expect("serialization should succeed");
+        let parsed: Value = serde_json::from_str(&json).expect("json should parse");
+
+        assert_eq!(parsed["commit_hash"], "abc123def456");
+        assert_eq!(parsed["short_commit_hash"], "abc123d");
+        assert_eq!(parsed["branch"], "main");
+        assert_eq!(parsed["upstream"], "origin/main");
+        assert_eq!(parsed["repository_url"], "https://example.com/repo.git");
+        assert_eq!(parsed["tag"], "v1.0.0");
+        assert_eq!(parsed["uses_lfs"], true);
+        assert_eq!(parsed["is_shallow"], true);
+        assert_eq!(
+            parsed["submodules"],
+            serde_json::json!([{
+                "path": "vendor/lib",
+                "sha": "0123456789abcdef",
+                "state": "modified",
+            }])
+        );
+    }
+
+    #[test]
+    fn git_info_serialization_skips_nones() {
+        let info = GitInfo {
+            commit_hash: None,
+            short_commit_hash: None,
+            branch: None,
+            upstream: None,
+            repository_url: None,
+            parsed_remote: None,
+            tag: None,
+            uses_lfs: false,
+            is_shallow: false,
+            ignored_count: None,
+            submodules: Vec::new(),
+        };
+
+        let json = serde_json::to_string(&info).expect("serialization should succeed");
+        let parsed: Value = serde_json::from_str(&json).expect("json should parse");
+
+        let object = parsed.as_object().expect("expected json object");
+        assert!(!object.contains_key("commit_hash"));
+        assert!(!object.contains_key("short_commit_hash"));
+        assert!(!object.contains_key("branch"));
+        assert!(!object.contains_key("upstream"));
+        assert!(!object.contains_key("repository_url"));
+        assert!(!object.contains_key("parsed_remote"));
+        assert!(!object.contains_key("tag"));
+        assert!(!object.contains_key("uses_lfs"));
+        assert!(!object.contains_key("is_shallow"));
+        assert!(!object.contains_key("submodules"));
+    }
+
+    #[test]
+    fn git_commands_use_resolved_program() {
+        use crate::revision_control::git::git_program;
+        use std::ffi::OsStr;
+
+        let command = git::git_command(&["status"], Path::new("."));
+        assert_eq!(command.as_std().get_program(), git_program().as_os_str());
+
+        let injected = OsStr::new("/opt/git/bin/git");
+        let command = git::git_command_with(
+            injected,
+            &GitCommandEnv::default(),
+            &["status"],
+            Path::new("."),
+        );
+        assert_eq!(command.as_std().get_program(), injected);
+        let args: Vec<&OsStr> = command.as_std().get_args().collect();
+        assert_eq!(args, vec![OsStr::new("status")]);
+    }
+
+    #[test]
+    fn git_commands_carry_configured_env() {
+        let env = GitCommandEnv::new(HashMap::from([(
+            "GIT_SSH_COMMAND".to_string(),
+            "ssh -o BatchMode=yes".to_string(),
+        )]));
+
+        let command = git::git_command_with(
+            std::ffi::OsStr::new("git"),
+            &env,
+            &["status"],
+            Path::new("."),
+        );
+        let envs: HashMap<String, String> = command
+            .as_std()
+            .get_envs()
+            .filter_map(|(key, value)| {
+                Some((key.to_str()?.to_string(), value?.to_str()?.to_string()))
+            })
+            .collect();
+
+        assert_eq!(
+            envs,
+            HashMap::from([
+                ("GIT_TERMINAL_PROMPT".to_string(), "0".to_string()),
+                ("GIT_OPTIONAL_LOCKS".to_string(), "0".to_string()),
+                (
+                    "GIT_SSH_COMMAND".to_string(),
+                    "ssh -o BatchMode=yes".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn remote_host_kind_classifies_hosts() {
+        let host_of = |url: &str| {
+            remote_host_kind(&GitInfo {
+                commit_hash: None,
+                short_commit_hash: None,
+                branch: None,
+                upstream: None,
+                repository_url: Some(url.to_string()),
+                parsed_remote: None,
+                tag: None,
+                uses_lfs: false,
+                is_shallow: false,
+                ignored_count: None,
+                submodules: Vec::new(),
+            })
+        };
+
+        assert_eq!(
+            host_of("https://github.com/openai/codex.git"),
+            RemoteHost::GitHub
+        );
+        assert_eq!(
+            host_of("git@github.com:openai/codex.git"),
+            RemoteHost::GitHub
+        );
+        assert_eq!(
+            host_of("https://www.github.com/openai/codex"),
+            RemoteHost::GitHub
+        );
+        assert_eq!(
+            host_of("https://gitlab.com/group/project.git"),
+            RemoteHost::GitLab
+        );
+        assert_eq!(
+            host_of("ssh://git@gitlab.example.com:2222/group/project.git"),
+            RemoteHost::Other
+        );
+        assert_eq!(
+            host_of("https://user@bitbucket.org/team/repo.git"),
+            RemoteHost::Bitbucket
+        );
+    }
+
+    #[tokio::test]
+    async fn collect_git_info_parses_remote_url() {
+        let temp_dir = tempdir().unwrap();
+        let repo_path = temp_dir.path();
+        git(repo_path, &["init", "--initial-branch", "main"]);
+        commit_as(repo_path, "Alice", "alice@example.com", "a.txt");
+        git(
+            repo_path,
+            &["remote", "add", "origin", "git@github.com:openai/codex.git"],
+        );
+        let backend = git_backend(repo_path.to_path_buf());
+
+        let expected = RemoteUrl {
+            scheme: RemoteScheme::Ssh,
+            host: "github.com".to_string(),
+            owner: "openai".to_string(),
+            repo: "codex".to_string(),
+        };
+        let git_info = collect_git_info(&backend, repo_path)
+            .await
+            .expect("git info should be collected");
+        assert_eq!(git_info.parsed_remote, Some(expected.clone()));
+        let blocking_info =
+            collect_git_info_blocking(&backend, repo_path).expect("git info should be collected");
+        assert_eq!(blocking_info.parsed_remote, Some(expected));
+
+        git(
+            repo_path,
+            &["remote", "set-url", "origin", "/srv/git/codex.git"],
+        );
+        let git_info = collect_git_info(&backend, repo_path)
+            .await
+            .expect("git info should be collected");
+        assert_eq!(
+            git_info.repository_url.as_deref(),
+            Some("/srv/git/codex.git")
+        );
+        assert_eq!(git_info.parsed_remote, None);
     }
 }
@@ -0,0 +1,1466 @@
+//! Utility to compute the current diff for the active revision-control backend.
+//!
+//! The helper detects whether the working directory is managed by Git or
+//! Darcs and shells out to the corresponding CLI to collect the diff. When no
+//! supported backend is detected the function returns `Ok((None, String::new()))`.
+
+use std::env;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::process::Stdio;
+
+use crate::git_info::git_command_env;
+use crate::git_info::omitted_file_placeholder;
+use crate::revision_control::RevisionControlKind;
+use crate::revision_control::darcs;
+use crate::revision_control::detect_revision_control_async;
+use crate::revision_control::fossil;
+use crate::revision_control::git::git_program;
+use async_stream::try_stream;
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::task::JoinSet;
+use tokio_stream::Stream;
+use tokio_stream::StreamExt;
+
+/// Read size for streamed git output.
+const DIFF_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Maximum number of repositories [`get_repo_diffs`] diffs at the same time.
+const MAX_CONCURRENT_REPO_DIFFS: usize = 4;
+
+/// Which untracked files a Git diff includes, mirroring `git --untracked-files`.
+///
+/// There is no `all` mode: git's `normal` mode differs from it only by collapsing
+/// untracked directories into one entry, which cannot be shown as a diff, so
+/// [`UntrackedFiles::Normal`] already lists each file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UntrackedFiles {
+    /// Only changes to tracked files.
+    No,
+    /// Every untracked file that is not ignored, diffed against `/dev/null`.
+    #[default]
+    Normal,
+}
+
+/// Which changes a diff covers, relative to the index and `HEAD`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiffTarget {
+    /// Unstaged changes (working tree against the index) plus untracked files.
+    #[default]
+    WorkingTree,
+    /// Staged changes only (index against `HEAD`, i.e. `git diff --cached`). Backends
+    /// without a staging area produce an empty diff.
+    Staged,
+    /// Staged and unstaged changes together (working tree against `HEAD`) plus
+    /// untracked files.
+    All,
+}
+
+/// Why a diff could not be computed.
+///
+/// Callers that work with `io::Result` can convert it with `io::Error::from`.
+#[derive(Debug, Error)]
+pub enum RepoDiffError {
+    #[error("{} command `{command}` failed with status {status}: {stderr}", backend.display_name())]
+    CommandFailed {
+        backend: RevisionControlKind,
+        command: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<RepoDiffError> for io::Error {
+    fn from(err: RepoDiffError) -> Self {
+        match err {
+            RepoDiffError::Io(err) => err,
+            other => io::Error::other(other),
+        }
+    }
+}
+
+/// Default for [`RepoDiffOptions::max_untracked_file_size`].
+const DEFAULT_MAX_UNTRACKED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Default for [`RepoDiffOptions::max_concurrent_untracked_diffs`].
+const DEFAULT_MAX_CONCURRENT_UNTRACKED_DIFFS: usize = 16;
+
+/// How much of an untracked file is checked for NUL bytes to decide it is binary,
+/// matching the amount git itself inspects.
+const BINARY_SNIFF_LEN: u64 = 8000;
+
+/// Options shared by the diff entry points.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RepoDiffOptions {
+    /// Globs whose matching paths are left out of the diff.
+    pub exclude: Vec<String>,
+    /// Whether untracked files are included (Git only).
+    pub untracked_files: UntrackedFiles,
+    /// Untracked files larger than this many bytes are replaced by a short
+    /// placeholder instead of their full contents (Git only). `None` means no cap;
+    /// the default is 1 MiB.
+    pub max_untracked_file_size: Option<u64>,
+    /// Most `git diff --no-index` processes run at once for untracked files (Git only),
+    /// so repositories with thousands of them do not exhaust file descriptors. Values
+    /// below 1 are treated as 1; the default is 16.
+    pub max_concurrent_untracked_diffs: usize,
+    /// Which changes are diffed; the default is [`DiffTarget::WorkingTree`].
+    pub target: DiffTarget,
+}
+
+impl Default for RepoDiffOptions {
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            untracked_files: UntrackedFiles::default(),
+            max_untracked_file_size: Some(DEFAULT_MAX_UNTRACKED_FILE_SIZE),
+            max_concurrent_untracked_diffs: DEFAULT_MAX_CONCURRENT_UNTRACKED_DIFFS,
+            target: DiffTarget::default(),
+        }
+    }
+}
+
+/// Return value of [`get_repo_diff`].
+///
+/// * `Option<RevisionControlKind>` – Detected backend (if any).
+/// * `String` – The concatenated diff (may be empty).
+///
+/// See [`RepoDiffOptions`] for what the diff includes.
+pub async fn get_repo_diff(
+    options: &RepoDiffOptions,
+) -> Result<(Option<RevisionControlKind>, String), RepoDiffError> {
+    let cwd = env::current_dir().map_err(RepoDiffError::Io)?;
+    get_repo_diff_in(&cwd, options).await
+}
+
+/// Like [`get_repo_diff`] but computes the diff for an explicit directory.
+pub async fn get_repo_diff_in(
+    cwd: &Path,
+    options: &RepoDiffOptions,
+) -> Result<(Option<RevisionControlKind>, String), RepoDiffError> {
+    compute_repo_diff(cwd, options, true).await
+}
+
+/// Diff several directories (e.g. the repositories of a multi-repo workspace)
+/// concurrently, with at most [`MAX_CONCURRENT_REPO_DIFFS`] in flight.
+///
+/// Results are returned in the order of `cwds`, each keyed by its directory.
+pub async fn get_repo_diffs(
+    cwds: &[PathBuf],
+    options: &RepoDiffOptions,
+) -> Vec<(
+    PathBuf,
+    Result<(Option<RevisionControlKind>, String), RepoDiffError>,
+)> {
+    let mut results: Vec<Option<Result<(Option<RevisionControlKind>, String), RepoDiffError>>> =
+        cwds.iter().map(|_| None).collect();
+    let mut join_set = JoinSet::new();
+    for (index, cwd) in cwds.iter().enumerate() {
+        if join_set.len() >= MAX_CONCURRENT_REPO_DIFFS
+            && let Some(Ok((done, result))) = join_set.join_next().await
+        {
+            results[done] = Some(result);
+        }
+        let cwd = cwd.clone();
+        let options = options.clone();
+        join_set.spawn(async move { (index, get_repo_diff_in(&cwd, &options).await) });
+    }
+    while let Some(joined) = join_set.join_next().await {
+        if let Ok((done, result)) = joined {
+            results[done] = Some(result);
+        }
+    }
+
+    cwds.iter()
+        .cloned()
+        .zip(results)
+        .map(|(cwd, result)| {
+            let result = result.unwrap_or_else(|| {
+                Err(RepoDiffError::Io(io::Error::other(
+                    "diff task failed to complete",
+                )))
+            });
+            (cwd, result)
+        })
+        .collect()
+}
+
+/// Lines added and deleted in one file. Both counts are `None` when no line counts
+/// are available, e.g. for binary files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffStat {
+    pub path: PathBuf,
+    pub added: Option<usize>,
+    pub deleted: Option<usize>,
+}
+
+/// Per-file line change counts for the workspace containing `cwd`, without
+/// producing the full patch.
+///
+/// Git counts come from `git diff --numstat` for tracked files plus one
+/// `--no-index` comparison per untracked file. Darcs counts come from
+/// `darcs whatsnew --summary`, which omits counts for added and binary files.
+/// Fossil workspaces yield no entries.
+pub async fn repo_diff_numstat(cwd: &Path) -> Result<Vec<DiffStat>, RepoDiffError> {
+    let Some(detected) = detect_revision_control_async(cwd).await else {
+        return Ok(Vec::new());
+    };
+
+    let stats = match detected.kind {
+        RevisionControlKind::Git if detected.is_bare => Vec::new(),
+        RevisionControlKind::Git => get_git_numstat(cwd).await?,
+        RevisionControlKind::Fossil => Vec::new(),
+        RevisionControlKind::Darcs => parse_darcs_summary(&darcs::workspace_summary(cwd).await?),
+    };
+    Ok(stats)
+}
+
+/// Change status of a file within a [`FileDiff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+/// A single `@@` hunk: its line ranges plus the raw body lines (with their
+/// `+`, `-`, or space prefix).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<String>,
+}
+
+/// Structured view of the changes made to one file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    /// Previous path for renamed files.
+    pub old_path: Option<PathBuf>,
+    pub status: FileStatus,
+    pub hunks: Vec<Hunk>,
+}
+
+/// Structured counterpart of [`get_repo_diff`] for the current directory.
+///
+/// The diff comes from the same invocation as the string API (without color)
+/// and is parsed into per-file hunks. Only Git output is in unified diff form,
+/// so Darcs workspaces yield no entries.
+pub async fn get_repo_diff_structured(
+    options: &RepoDiffOptions,
+) -> Result<(Option<RevisionControlKind>, Vec<FileDiff>), RepoDiffError> {
+    let cwd = env::current_dir().map_err(RepoDiffError::Io)?;
+    get_repo_diff_structured_in(&cwd, options).await
+}
+
+/// Like [`get_repo_diff_structured`] but computes the diff for an explicit directory.
+pub async fn get_repo_diff_structured_in(
+    cwd: &Path,
+    options: &RepoDiffOptions,
+) -> Result<(Option<RevisionControlKind>, Vec<FileDiff>), RepoDiffError> {
+    let (kind, diff) = compute_repo_diff(cwd, options, false).await?;
+    let files = match kind {
+        Some(RevisionControlKind::Git) => parse_git_diff(&diff),
+        _ => Vec::new(),
+    };
+    Ok((kind, files))
+}
+
+async fn compute_repo_diff(
+    cwd: &Path,
+    options: &RepoDiffOptions,
+    color: bool,
+) -> Result<(Option<RevisionControlKind>, String), RepoDiffError> {
+    let detected = detect_revision_control_async(cwd).await;
+
+    let Some(detected) = detected else {
+        return Ok((None, String::new()));
+    };
+
+    let diff = match detected.kind {
+        // A bare repository has no working tree, so there is nothing to diff.
+        RevisionControlKind::Git if detected.is_bare => String::new(),
+        RevisionControlKind::Git => get_git_diff(cwd, options, color).await?,
+        kind if options.target == DiffTarget::Staged => {
+            tracing::debug!(
+                "{} has no staging area; staged diff is empty",
+                kind.display_name()
+            );
+            String::new()
+        }
+        RevisionControlKind::Fossil => fossil::workspace_diff(cwd).await?,
+        RevisionControlKind::Darcs => darcs::workspace_diff(cwd, &options.exclude, color).await?,
+    };
+
+    Ok((Some(detected.kind), diff))
+}
+
+/// Streaming counterpart of [`get_repo_diff_in`] that yields the diff in chunks
+/// instead of buffering it, for changesets too large to hold in one `String`.
+///
+/// Git output is forwarded as git produces it, split on line boundaries. Fossil
+/// and Darcs diffs are yielded as a single chunk.
+pub fn stream_repo_diff(
+    cwd: &Path,
+    options: &RepoDiffOptions,
+) -> impl Stream<Item = Result<String, RepoDiffError>> {
+    let cwd = cwd.to_path_buf();
+    let options = options.clone();
+    try_stream! {
+        let Some(detected) = detect_revision_control_async(&cwd).await else {
+            return;
+        };
+        match detected.kind {
+            RevisionControlKind::Git if detected.is_bare => {}
+            RevisionControlKind::Git => {
+                let chunks = stream_git_diff(cwd, options, true);
+                tokio::pin!(chunks);
+                while let Some(chunk) = chunks.next().await {
+                    yield chunk?;
+                }
+            }
+            kind if options.target == DiffTarget::Staged => {
+                tracing::debug!("{} has no staging area; staged diff is empty", kind.display_name());
+            }
+            RevisionControlKind::Fossil => {
+                yield fossil::workspace_diff(&cwd).await?;
+            }
+            RevisionControlKind::Darcs => {
+                yield darcs::workspace_diff(&cwd, &options.exclude, true).await?;
+            }
+        }
+    }
+}
+
+/// Diff tracked changes plus, unless `options.untracked_files` is `No`, every
+/// untracked file under `cwd`. Untracked files above
+/// `options.max_untracked_file_size` are summarized by a placeholder.
+///
+/// `options.target` picks the tracked diff: `git diff`, `git diff --cached`, or
+/// `git diff HEAD`. Staged diffs leave out untracked files.
+///
+/// Untracked files are listed with `--exclude-standard`, so `.gitignore` files,
+/// `.git/info/exclude`, and `core.excludesFile` are all honored.
+///
+/// In a repository without commits there is nothing to diff tracked files
+/// against, so every file (including ones already staged) is shown as new.
+async fn get_git_diff(
+    cwd: &Path,
+    options: &RepoDiffOptions,
+    color: bool,
+) -> Result<String, RepoDiffError> {
+    let chunks = stream_git_diff(cwd.to_path_buf(), options.clone(), color);
+    tokio::pin!(chunks);
+    let mut diff = String::new();
+    while let Some(chunk) = chunks.next().await {
+        diff.push_str(&chunk?);
+    }
+    Ok(diff)
+}
+
+/// Chunked form of [`get_git_diff`]: the tracked diff streams straight from git,
+/// followed by one chunk per untracked file.
+fn stream_git_diff(
+    cwd: PathBuf,
+    options: RepoDiffOptions,
+    color: bool,
+) -> impl Stream<Item = Result<String, RepoDiffError>> {
+    try_stream! {
+        if !inside_git_repo(&cwd).await? {
+            return;
+        }
+
+        let color_arg = if color { "--color" } else { "--no-color" };
+        let pathspecs = exclude_pathspecs(&options.exclude);
+        let unborn = !has_head_commit(&cwd).await;
+        let mut tracked_args = vec!["diff".to_string(), color_arg.to_string()];
+        match options.target {
+            DiffTarget::WorkingTree => {}
+            DiffTarget::Staged => tracked_args.push("--cached".to_string()),
+            DiffTarget::All => tracked_args.push("HEAD".to_string()),
+        }
+        tracked_args.extend(pathspecs.iter().cloned());
+        let include_untracked = options.untracked_files != UntrackedFiles::No
+            && options.target != DiffTarget::Staged;
+        let mut untracked_args = vec!["ls-files".to_string()];
+        if include_untracked {
+            untracked_args.extend(["--others".to_string(), "--exclude-standard".to_string()]);
+        }
+        if unborn {
+            untracked_args.push("--cached".to_string());
+        }
+        untracked_args.extend(pathspecs);
+
+        // List untracked files while the tracked diff streams.
+        let untracked_listing = {
+            let cwd = cwd.clone();
+            tokio::spawn(async move {
+                if include_untracked || unborn {
+                    run_git_capture_stdout(&cwd, untracked_args).await
+                } else {
+                    Ok(String::new())
+                }
+            })
+        };
+
+        if !unborn {
+            let tracked = stream_git_stdout(cwd.clone(), tracked_args);
+            tokio::pin!(tracked);
+            while let Some(chunk) = tracked.next().await {
+                yield chunk?;
+            }
+        }
+
+        let untracked_output = untracked_listing
+            .await
+            .map_err(|err| RepoDiffError::Io(io::Error::other(err)))??;
+
+        let null_device: &Path = if cfg!(windows) {
+            Path::new("NUL")
+        } else {
+            Path::new("/dev/null")
+        };
+
+        let null_path = null_device.to_str().unwrap_or("/dev/null").to_string();
+        let max_size = options.max_untracked_file_size;
+        let max_concurrent = options.max_concurrent_untracked_diffs.max(1);
+        let mut files = untracked_output
+            .split('\n')
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+        let mut join_set: JoinSet<Result<String, RepoDiffError>> = JoinSet::new();
+        loop {
+            while join_set.len() < max_concurrent
+                && let Some(file) = files.next()
+            {
+                let cwd = cwd.clone();
+                let null_path = null_path.clone();
+                let file = file.to_string();
+                join_set.spawn(async move {
+                    if let Ok(metadata) = tokio::fs::metadata(cwd.join(&file)).await {
+                        if max_size.is_some_and(|max_size| metadata.len() > max_size) {
+                            return Ok(omitted_file_placeholder(&file, metadata.len()));
+                        }
+                        if is_binary_file(&cwd.join(&file)).await {
+                            return Ok(untracked_binary_placeholder(&file, metadata.len()));
+                        }
+                    }
+                    run_git_capture_diff(
+                        &cwd,
+                        vec![
+                            "diff".into(),
+                            color_arg.into(),
+                            "--no-index".into(),
+                            "--".into(),
+                            null_path,
+                            file,
+                        ],
+                    )
+                    .await
+                });
+            }
+            let Some(res) = join_set.join_next().await else {
+                break;
+            };
+            match res {
+                Ok(Ok(diff)) => yield diff,
+                Ok(Err(RepoDiffError::Io(err))) if err.kind() == io::ErrorKind::NotFound => {}
+                Ok(Err(err)) => Err(err)?,
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// Numstat counterpart of [`get_git_diff`] with the default [`RepoDiffOptions`].
+async fn get_git_numstat(cwd: &Path) -> Result<Vec<DiffStat>, RepoDiffError> {
+    if !inside_git_repo(cwd).await? {
+        return Ok(Vec::new());
+    }
+
+    let unborn = !has_head_commit(cwd).await;
+    let mut stats = if unborn {
+        Vec::new()
+    } else {
+        parse_git_numstat(&run_git_capture_diff(cwd, ["diff", "--numstat", "-z"]).await?)
+    };
+
+    let mut untracked_args = vec!["ls-files", "--others", "--exclude-standard"];
+    if unborn {
+        untracked_args.push("--cached");
+    }
+    let untracked_output = run_git_capture_stdout(cwd, untracked_args).await?;
+
+    let null_path = if cfg!(windows) { "NUL" } else { "/dev/null" };
+    let mut files = untracked_output
+        .split('\n')
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    let mut join_set: JoinSet<Result<DiffStat, RepoDiffError>> = JoinSet::new();
+    loop {
+        while join_set.len() < DEFAULT_MAX_CONCURRENT_UNTRACKED_DIFFS
+            && let Some(file) = files.next()
+        {
+            let cwd = cwd.to_path_buf();
+            let file = file.to_string();
+            join_set.spawn(async move {
+                let output = run_git_capture_diff(
+                    &cwd,
+                    [
+                        "diff",
+                        "--no-index",
+                        "--numstat",
+                        "-z",
+                        "--",
+                        null_path,
+                        &file,
+                    ],
+                )
+                .await?;
+                // The reported path varies between git versions for `/dev/null`
+                // comparisons, so keep the listed name and take only the counts.
+                let (added, deleted) = parse_git_numstat(&output)
+                    .into_iter()
+                    .next()
+                    .map_or((Some(0), Some(0)), |stat| (stat.added, stat.deleted));
+                Ok(DiffStat {
+                    path: PathBuf::from(file),
+                    added,
+                    deleted,
+                })
+            });
+        }
+        let Some(res) = join_set.join_next().await else {
+            break;
+        };
+        match res {
+            Ok(Ok(stat)) => stats.push(stat),
+            Ok(Err(RepoDiffError::Io(err))) if err.kind() == io::ErrorKind::NotFound => {}
+            Ok(Err(err)) => return Err(err),
+            Err(_) => {}
+        }
+    }
+    stats.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(stats)
+}
+
+/// Parse `git diff --numstat -z` output. Binary files are reported by git as
+/// `-` and become `None` counts; renames are keyed by their new path.
+fn parse_git_numstat(output: &str) -> Vec<DiffStat> {
+    let mut stats = Vec::new();
+    let mut fields = output.split('\0');
+    while let Some(record) = fields.next() {
+        let mut parts = record.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        // With `-z`, a rename leaves the path empty and follows the record with
+        // the old and new paths as separate fields.
+        let path = if path.is_empty() {
+            let _old_path = fields.next();
+            match fields.next() {
+                Some(new_path) => new_path,
+                None => break,
+            }
+        } else {
+            path
+        };
+        stats.push(DiffStat {
+            path: PathBuf::from(path),
+            added: added.parse().ok(),
+            deleted: deleted.parse().ok(),
+        });
+    }
+    stats
+}
+
+/// Parse `darcs whatsnew --summary` lines such as `M ./src/lib.rs -3 +5`.
+///
+/// A missing `-N` or `+N` means zero lines of that kind, but a line with neither
+/// (added files, binary changes) has no counts. Moves are skipped.
+fn parse_darcs_summary(summary: &str) -> Vec<DiffStat> {
+    summary
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let status = tokens.next()?;
+            if status.starts_with("./") {
+                return None;
+            }
+            let path = tokens.next()?.strip_prefix("./")?;
+            let mut added = None;
+            let mut deleted = None;
+            for token in tokens {
+                if let Some(count) = token.strip_prefix('+') {
+                    added = count.parse().ok();
+                } else if let Some(count) = token.strip_prefix('-') {
+                    deleted = count.parse().ok();
+                }
+            }
+            if added.is_some() || deleted.is_some() {
+                added = added.or(Some(0));
+                deleted = deleted.or(Some(0));
+            }
+            Some(DiffStat {
+                path: PathBuf::from(path),
+                added,
+                deleted,
+            })
+        })
+        .collect()
+}
+
+/// Diff stanza summarizing an untracked binary file, with a `diff --git` header so
+/// structured parsing treats it as its own added file.
+fn untracked_binary_placeholder(path: &str, size: u64) -> String {
+    format!(
+        "diff --git a/{path} b/{path}\nnew file mode 100644\nBinary file {path} (untracked, {size} bytes)\n"
+    )
+}
+
+/// Whether the regular file at `path` looks binary: a NUL byte within its first
+/// [`BINARY_SNIFF_LEN`] bytes. Symlinks are never binary; git diffs their target path.
+async fn is_binary_file(path: &Path) -> bool {
+    let is_regular = tokio::fs::symlink_metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_file());
+    if !is_regular {
+        return false;
+    }
+    let Ok(file) = tokio::fs::File::open(path).await else {
+        return false;
+    };
+    let mut head = Vec::new();
+    if file
+        .take(BINARY_SNIFF_LEN)
+        .read_to_end(&mut head)
+        .await
+        .is_err()
+    {
+        return false;
+    }
+    head.contains(&0)
+}
+
+/// Forward the stdout of a `git` diff command as it is produced. Chunks end on
+/// line boundaries so multi-byte characters are never split. Exit status 1
+/// (differences present) counts as success, as in [`run_git_capture_diff`].
+fn stream_git_stdout(
+    cwd: PathBuf,
+    args: Vec<String>,
+) -> impl Stream<Item = Result<String, RepoDiffError>> {
+    try_stream! {
+        let mut child = git_command(&cwd)
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(RepoDiffError::Io)?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| RepoDiffError::Io(io::Error::other("git stdout was not captured")))?;
+        // Drain stderr concurrently so a chatty git cannot block on a full pipe.
+        let stderr = child.stderr.take().map(|mut stderr| {
+            tokio::spawn(async move {
+                let mut captured = Vec::new();
+                let _ = stderr.read_to_end(&mut captured).await;
+                captured
+            })
+        });
+
+        let mut buf = vec![0u8; DIFF_CHUNK_SIZE];
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let read = stdout.read(&mut buf).await.map_err(RepoDiffError::Io)?;
+            if read == 0 {
+                break;
+            }
+            pending.extend_from_slice(&buf[..read]);
+            if let Some(last_newline) = pending.iter().rposition(|byte| *byte == b'\n') {
+                let rest = pending.split_off(last_newline + 1);
+                yield String::from_utf8_lossy(&pending).into_owned();
+                pending = rest;
+            }
+        }
+        if !pending.is_empty() {
+            yield String::from_utf8_lossy(&pending).into_owned();
+        }
+
+        let status = child.wait().await.map_err(RepoDiffError::Io)?;
+        if !(status.success() || status.code() == Some(1)) {
+            let stderr = match stderr {
+                Some(task) => task.await.unwrap_or_default(),
+                None => Vec::new(),
+            };
+            Err(git_command_failed(&args, status, &stderr))?;
+        }
+    }
+}
+
+/// Parse `git diff --no-color` output into per-file structured diffs.
+fn parse_git_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let path = rest.rsplit_once(" b/").map_or(rest, |(_, new)| new);
+            files.push(FileDiff {
+                path: PathBuf::from(path),
+                old_path: None,
+                status: FileStatus::Modified,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        // Header lines only precede the first hunk, so anything after it is body.
+        if let Some(hunk) = file.hunks.last_mut() {
+            if let Some(next) = parse_hunk_header(line) {
+                file.hunks.push(next);
+            } else {
+                hunk.lines.push(line.to_string());
+            }
+            continue;
+        }
+
+        if line.starts_with("new file mode") {
+            file.status = FileStatus::Added;
+        } else if line.starts_with("deleted file mode") {
+            file.status = FileStatus::Deleted;
+        } else if let Some(from) = line.strip_prefix("rename from ") {
+            file.status = FileStatus::Renamed;
+            file.old_path = Some(PathBuf::from(from));
+        } else if let Some(to) = line.strip_prefix("rename to ") {
+            file.path = PathBuf::from(to);
+        } else if let Some(new) = line.strip_prefix("+++ b/") {
+            file.path = PathBuf::from(new);
+        } else if let Some(hunk) = parse_hunk_header(line) {
+            file.hunks.push(hunk);
+        }
+    }
+    files
+}
+
+/// Parse a `@@ -a,b +c,d @@` hunk header. Omitted lengths default to 1.
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let (ranges, _) = line.strip_prefix("@@ -")?.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let (old_start, old_lines) = parse_hunk_range(old)?;
+    let (new_start, new_lines) = parse_hunk_range(new)?;
+    Some(Hunk {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        lines: Vec::new(),
+    })
+}
+
+fn parse_hunk_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Build the trailing pathspec arguments that exclude each glob in `exclude`.
+///
+/// Returns no arguments when nothing is excluded so the default invocation is
+/// unchanged. Globs are interpreted relative to the working directory.
+fn exclude_pathspecs(exclude: &[String]) -> Vec<String> {
+    if exclude.is_empty() {
+        return Vec::new();
+    }
+
+    let mut args = vec!["--".to_string(), ".".to_string()];
+    args.extend(exclude.iter().map(|glob| format!(":(exclude){glob}")));
+    args
+}
+
+/// Helper that executes `git` with the given `args` and returns `stdout` as a
+/// UTF-8 string. Any non-zero exit status is considered an *error*.
+async fn run_git_capture_stdout<I, S>(cwd: &Path, args: I) -> Result<String, RepoDiffError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_git_capture(cwd, args, false).await
+}
+
+/// Like [`run_git_capture_stdout`] but treats exit status 1 as success and
+/// returns stdout. Git returns 1 for diffs when differences are present.
+async fn run_git_capture_diff<I, S>(cwd: &Path, args: I) -> Result<String, RepoDiffError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_git_capture(cwd, args, true).await
+}
+
+async fn run_git_capture<I, S>(
+    cwd: &Path,
+    args: I,
+    allow_differences: bool,
+) -> Result<String, RepoDiffError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args: Vec<OsString> = args
+        .into_iter()
+        .map(|arg| arg.as_ref().to_os_string())
+        .collect();
+    let output = git_command(cwd)
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(RepoDiffError::Io)?;
+
+    if output.status.success() || (allow_differences && output.status.code() == Some(1)) {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(git_command_failed(&args, output.status, &output.stderr))
+    }
+}
+
+/// A [`RepoDiffError::CommandFailed`] for `git <args>`.
+fn git_command_failed<S: AsRef<OsStr>>(
+    args: &[S],
+    status: ExitStatus,
+    stderr: &[u8],
+) -> RepoDiffError {
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| arg.as_ref().to_string_lossy().into_owned())
+        .collect();
+    RepoDiffError::CommandFailed {
+        backend: RevisionControlKind::Git,
+        command: format!("git {}", args.join(" ")),
+        status,
+        stderr: String::from_utf8_lossy(stderr).trim().to_string(),
+    }
+}
+
+/// `git` command for `cwd` carrying the environment from [`git_command_env`].
+fn git_command(cwd: &Path) -> Command {
+    let mut command = Command::new(git_program());
+    command.current_dir(cwd).envs(git_command_env());
+    command
+}
+
+/// Whether `HEAD` points at a commit; false for a freshly initialized repository.
+async fn has_head_commit(cwd: &Path) -> bool {
+    git_command(cwd)
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+/// Determine if the specified directory is inside a Git repository.
+async fn inside_git_repo(cwd: &Path) -> Result<bool, RepoDiffError> {
+    let status = git_command(cwd)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+
+    match status {
+        Ok(s) if s.success() => Ok(true),
+        Ok(_) => Ok(false),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false), // git not installed
+        Err(e) => Err(RepoDiffError::Io(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::process::Command as StdCommand;
+    use tempfile::tempdir;
+
+    fn run_git(repo: &Path, args: &[&str]) {
+        let status = StdCommand::new("git")
+            .current_dir(repo)
+            .args(args)
+            .status()
+            .expect("git command");
+        assert!(status.success(), "git command failed: {args:?}");
+    }
+
+    fn init_repo_with_commit(repo: &Path, files: &[(&str, &str)]) {
+        run_git(repo, &["init", "--initial-branch=main"]);
+        for (name, contents) in files {
+            std::fs::write(repo.join(name), contents).unwrap();
+        }
+        run_git(repo, &["add", "."]);
+        run_git(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn excluded_paths_are_omitted_from_git_diff() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("kept.txt", "kept\n"), ("Cargo.lock", "lock\n")]);
+
+        std::fs::write(repo.join("kept.txt"), "kept changed\n").unwrap();
+        std::fs::write(repo.join("Cargo.lock"), "lock changed\n").unwrap();
+        std::fs::write(repo.join("new.snap"), "snapshot\n").unwrap();
+
+        let (kind, diff) = get_repo_diff_in(
+            repo,
+            &RepoDiffOptions {
+                exclude: vec!["Cargo.lock".to_string(), "*.snap".to_string()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(kind, Some(RevisionControlKind::Git));
+        assert!(diff.contains("kept changed"));
+        assert!(!diff.contains("lock changed"));
+        assert!(!diff.contains("new.snap"));
+    }
+
+    #[tokio::test]
+    async fn bare_repository_diff_is_empty() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        run_git(repo, &["init", "--bare"]);
+
+        let result = get_repo_diff_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result, (Some(RevisionControlKind::Git), String::new()));
+    }
+
+    #[tokio::test]
+    async fn repo_diffs_are_keyed_by_directory() {
+        let temp = tempdir().unwrap();
+        let first = temp.path().join("first");
+        let second = temp.path().join("second");
+        for (repo, name) in [(&first, "first.txt"), (&second, "second.txt")] {
+            std::fs::create_dir(repo).unwrap();
+            init_repo_with_commit(repo, &[(name, "initial\n")]);
+            std::fs::write(repo.join(name), format!("{name} changed\n")).unwrap();
+        }
+
+        let diffs = get_repo_diffs(
+            &[first.clone(), second.clone()],
+            &RepoDiffOptions::default(),
+        )
+        .await;
+
+        assert_eq!(diffs.len(), 2);
+        let (first_path, first_diff) = &diffs[0];
+        let (second_path, second_diff) = &diffs[1];
+        assert_eq!(first_path, &first);
+        assert_eq!(second_path, &second);
+        let (kind, first_diff) = first_diff.as_ref().unwrap();
+        assert_eq!(kind, &Some(RevisionControlKind::Git));
+        assert!(first_diff.contains("first.txt changed"));
+        assert!(!first_diff.contains("second.txt"));
+        let (_, second_diff) = second_diff.as_ref().unwrap();
+        assert!(second_diff.contains("second.txt changed"));
+        assert!(!second_diff.contains("first.txt"));
+    }
+
+    #[tokio::test]
+    async fn failed_git_commands_report_status_and_stderr() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("file.txt", "contents\n")]);
+
+        let err = run_git_capture_diff(repo, ["diff", "--no-such-option"])
+            .await
+            .expect_err("unknown option should fail");
+
+        match err {
+            RepoDiffError::CommandFailed {
+                backend,
+                command,
+                status,
+                stderr,
+            } => {
+                assert_eq!(backend, RevisionControlKind::Git);
+                assert_eq!(command, "git diff --no-such-option");
+                assert_eq!(status.code(), Some(129));
+                assert!(stderr.contains("no-such-option"), "stderr: {stderr}");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn streamed_diff_matches_buffered_diff() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        let original: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+        init_repo_with_commit(repo, &[("big.txt", &original)]);
+        std::fs::write(repo.join("big.txt"), original.replace("line 1", "LINE 1")).unwrap();
+        std::fs::write(repo.join("untracked.txt"), "untracked\n").unwrap();
+
+        let chunks: Vec<String> = stream_repo_diff(repo, &RepoDiffOptions::default())
+            .collect::<Result<Vec<String>, RepoDiffError>>()
+            .await
+            .unwrap();
+        let (_, buffered) = get_repo_diff_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.ends_with('\n')));
+        assert_eq!(chunks.concat(), buffered);
+    }
+
+    #[tokio::test]
+    async fn untracked_files_mode_controls_untracked_output() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "tracked\n")]);
+        std::fs::write(repo.join("tracked.txt"), "tracked changed\n").unwrap();
+        std::fs::write(repo.join("build.log"), "artifact\n").unwrap();
+
+        let (_, without) = get_repo_diff_in(
+            repo,
+            &RepoDiffOptions {
+                untracked_files: UntrackedFiles::No,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(without.contains("tracked changed"));
+        assert!(!without.contains("build.log"));
+
+        let (_, with) = get_repo_diff_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+        assert!(with.contains("tracked changed"));
+        assert!(with.contains("build.log"));
+    }
+
+    #[tokio::test]
+    async fn diff_target_selects_staged_unstaged_or_both() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("staged.txt", "old\n"), ("unstaged.txt", "old\n")]);
+        std::fs::write(repo.join("staged.txt"), "index change\n").unwrap();
+        run_git(repo, &["add", "staged.txt"]);
+        std::fs::write(repo.join("unstaged.txt"), "worktree change\n").unwrap();
+        std::fs::write(repo.join("untracked.txt"), "untracked\n").unwrap();
+
+        let diff_for = |target| async move {
+            let options = RepoDiffOptions {
+                target,
+                ..Default::default()
+            };
+            get_repo_diff_in(repo, &options).await.unwrap().1
+        };
+
+        let working_tree = diff_for(DiffTarget::WorkingTree).await;
+        assert!(!working_tree.contains("index change"));
+        assert!(working_tree.contains("worktree change"));
+        assert!(working_tree.contains("untracked.txt"));
+
+        let staged = diff_for(DiffTarget::Staged).await;
+        assert!(staged.contains("index change"));
+        assert!(!staged.contains("worktree change"));
+        assert!(!staged.contains("untracked.txt"));
+
+        let all = diff_for(DiffTarget::All).await;
+        assert!(all.contains("index change"));
+        assert!(all.contains("worktree change"));
+        assert!(all.contains("untracked.txt"));
+    }
+
+    #[tokio::test]
+    async fn large_untracked_files_are_replaced_by_placeholder() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "tracked\n")]);
+        let large = "large content\n".repeat(1024);
+        std::fs::write(repo.join("large.bin"), &large).unwrap();
+        std::fs::write(repo.join("small.txt"), "small content\n").unwrap();
+
+        let (_, uncapped) = get_repo_diff_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+        assert!(uncapped.contains("large content"));
+
+        let (_, capped) = get_repo_diff_in(
+            repo,
+            &RepoDiffOptions {
+                max_untracked_file_size: Some(1024),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!capped.contains("large content"));
+        assert!(capped.contains(&omitted_file_placeholder("large.bin", large.len() as u64)));
+        assert!(capped.contains("small content"));
+    }
+
+    #[tokio::test]
+    async fn binary_untracked_files_are_summarized() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "tracked\n")]);
+        let image = [0x89, b'P', b'N', b'G', 0, 0, 0, 13, 0xff];
+        std::fs::write(repo.join("image.png"), image).unwrap();
+        std::fs::write(repo.join("notes.txt"), "plain text\n").unwrap();
+
+        let (_, diff) = get_repo_diff_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+
+        assert!(diff.contains(&untracked_binary_placeholder(
+            "image.png",
+            image.len() as u64
+        )));
+        assert!(!diff.contains("Binary files"));
+        assert!(diff.contains("+plain text"));
+    }
+
+    #[tokio::test]
+    async fn many_untracked_files_are_diffed_with_bounded_concurrency() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "tracked\n")]);
+        let count = 500;
+        for i in 0..count {
+            std::fs::write(repo.join(format!("file-{i:03}.txt")), format!("line {i}\n")).unwrap();
+        }
+
+        let (_, diff) = get_repo_diff_in(
+            repo,
+            &RepoDiffOptions {
+                max_concurrent_untracked_diffs: 4,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(diff.matches("diff --git").count(), count);
+        assert!(diff.contains("+line 0\n"));
+        assert!(diff.contains(&format!("+line {}\n", count - 1)));
+
+        let stats = repo_diff_numstat(repo).await.unwrap();
+        assert_eq!(stats.len(), count);
+    }
+
+    #[tokio::test]
+    async fn unborn_repository_diff_lists_new_files() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        run_git(repo, &["init", "--initial-branch=main"]);
+        std::fs::write(repo.join("untracked.txt"), "untracked\n").unwrap();
+        std::fs::write(repo.join("staged.txt"), "staged\n").unwrap();
+        run_git(repo, &["add", "staged.txt"]);
+
+        let (kind, files) = get_repo_diff_structured_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(kind, Some(RevisionControlKind::Git));
+        let mut paths: Vec<(PathBuf, FileStatus)> = files
+            .into_iter()
+            .map(|file| (file.path, file.status))
+            .collect();
+        paths.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            paths,
+            vec![
+                (PathBuf::from("staged.txt"), FileStatus::Added),
+                (PathBuf::from("untracked.txt"), FileStatus::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_git_diff_reports_status_and_hunks() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
+ fn keep() {}
+-fn old() {}
++fn new() {}
+@@ -10 +10,2 @@ impl Foo {
+--- not a header
++++ not a header either
+diff --git a/old.txt b/new.txt
+similarity index 90%
+rename from old.txt
+rename to new.txt
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index 3333333..0000000
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+diff --git a/added.txt b/added.txt
+new file mode 100644
+index 0000000..4444444
+--- /dev/null
++++ b/added.txt
+@@ -0,0 +1 @@
++hi
+";
+
+        let files = parse_git_diff(diff);
+
+        assert_eq!(
+            files,
+            vec![
+                FileDiff {
+                    path: PathBuf::from("src/lib.rs"),
+                    old_path: None,
+                    status: FileStatus::Modified,
+                    hunks: vec![
+                        Hunk {
+                            old_start: 1,
+                            old_lines: 2,
+                            new_start: 1,
+                            new_lines: 2,
+                            lines: vec![
+                                " fn keep() {}".to_string(),
+                                "-fn old() {}".to_string(),
+                                "+fn new() {}".to_string(),
+                            ],
+                        },
+                        Hunk {
+                            old_start: 10,
+                            old_lines: 1,
+                            new_start: 10,
+                            new_lines: 2,
+                            lines: vec![
+                                "--- not a header".to_string(),
+                                "+++ not a header either".to_string(),
+                            ],
+                        },
+                    ],
+                },
+                FileDiff {
+                    path: PathBuf::from("new.txt"),
+                    old_path: Some(PathBuf::from("old.txt")),
+                    status: FileStatus::Renamed,
+                    hunks: Vec::new(),
+                },
+                FileDiff {
+                    path: PathBuf::from("gone.txt"),
+                    old_path: None,
+                    status: FileStatus::Deleted,
+                    hunks: vec![Hunk {
+                        old_start: 1,
+                        old_lines: 1,
+                        new_start: 0,
+                        new_lines: 0,
+                        lines: vec!["-bye".to_string()],
+                    }],
+                },
+                FileDiff {
+                    path: PathBuf::from("added.txt"),
+                    old_path: None,
+                    status: FileStatus::Added,
+                    hunks: vec![Hunk {
+                        old_start: 0,
+                        old_lines: 0,
+                        new_start: 1,
+                        new_lines: 1,
+                        lines: vec!["+hi".to_string()],
+                    }],
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn structured_diff_covers_tracked_and_untracked_files() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "one\n")]);
+
+        std::fs::write(repo.join("tracked.txt"), "two\n").unwrap();
+        std::fs::write(repo.join("untracked.txt"), "new\n").unwrap();
+
+        let (kind, files) = get_repo_diff_structured_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(kind, Some(RevisionControlKind::Git));
+        let summary: Vec<(PathBuf, FileStatus, usize)> = files
+            .iter()
+            .map(|file| (file.path.clone(), file.status, file.hunks.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (PathBuf::from("tracked.txt"), FileStatus::Modified, 1),
+                (PathBuf::from("untracked.txt"), FileStatus::Added, 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn structured_diff_keeps_untracked_binary_separate() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "one\n")]);
+
+        std::fs::write(repo.join("tracked.txt"), "two\n").unwrap();
+        std::fs::write(
+            repo.join("image.png"),
+            [0x89, b'P', b'N', b'G', 0, 0, 0, 13],
+        )
+        .unwrap();
+
+        let (_, files) = get_repo_diff_structured_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+
+        let summary: Vec<(PathBuf, FileStatus, usize)> = files
+            .iter()
+            .map(|file| (file.path.clone(), file.status, file.hunks.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (PathBuf::from("tracked.txt"), FileStatus::Modified, 1),
+                (PathBuf::from("image.png"), FileStatus::Added, 0),
+            ]
+        );
+        assert_eq!(files[0].hunks[0].lines, vec!["-one", "+two"]);
+    }
+
+    #[tokio::test]
+    async fn numstat_counts_tracked_binary_and_untracked_files() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "one\ntwo\n")]);
+        std::fs::write(repo.join("image.bin"), [0u8, 1, 2, 3]).unwrap();
+        run_git(repo, &["add", "image.bin"]);
+
+        std::fs::write(repo.join("tracked.txt"), "one\nthree\nfour\n").unwrap();
+        std::fs::write(repo.join("image.bin"), [0u8, 4, 5, 6]).unwrap();
+        std::fs::write(repo.join("untracked.txt"), "a\nb\n").unwrap();
+
+        let stats = repo_diff_numstat(repo).await.unwrap();
+
+        assert_eq!(
+            stats,
+            vec![
+                DiffStat {
+                    path: PathBuf::from("image.bin"),
+                    added: None,
+                    deleted: None,
+                },
+                DiffStat {
+                    path: PathBuf::from("tracked.txt"),
+                    added: Some(2),
+                    deleted: Some(1),
+                },
+                DiffStat {
+                    path: PathBuf::from("untracked.txt"),
+                    added: Some(2),
+                    deleted: Some(0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_darcs_summary_reads_counts() {
+        let summary = "\
+M ./src/lib.rs -3 +5
+M ./README +2
+A ./new.txt
+M ./logo.png
+ ./old.txt -> ./moved.txt
+";
+
+        assert_eq!(
+            parse_darcs_summary(summary),
+            vec![
+                DiffStat {
+                    path: PathBuf::from("src/lib.rs"),
+                    added: Some(5),
+                    deleted: Some(3),
+                },
+                DiffStat {
+                    path: PathBuf::from("README"),
+                    added: Some(2),
+                    deleted: Some(0),
+                },
+                DiffStat {
+                    path: PathBuf::from("new.txt"),
+                    added: None,
+                    deleted: None,
+                },
+                DiffStat {
+                    path: PathBuf::from("logo.png"),
+                    added: None,
+                    deleted: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn untracked_files_honor_info_exclude() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        init_repo_with_commit(repo, &[("tracked.txt", "tracked\n")]);
+
+        std::fs::write(repo.join(".git/info/exclude"), "local-only.txt\n").unwrap();
+        std::fs::write(repo.join("local-only.txt"), "private\n").unwrap();
+        std::fs::write(repo.join("visible.txt"), "visible\n").unwrap();
+
+        let (_, diff) = get_repo_diff_in(repo, &RepoDiffOptions::default())
+            .await
+            .unwrap();
+
+        assert!(diff.contains("visible.txt"));
+        assert!(!diff.contains("local-only.txt"));
+    }
+}
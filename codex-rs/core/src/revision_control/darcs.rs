@@ -1,22 +1,43 @@
+use std::collections::HashSet;
 use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::io;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
-use std::sync::OnceLock;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::PoisonError;
 
+use crate::git_info::RepoDiffError;
+use crate::revision_control::RevisionControlKind;
 use codex_protocol::protocol::DarcsInfo;
 use tokio::process::Command;
 use tokio::time::Duration as TokioDuration;
 use tokio::time::timeout;
 use tracing::warn;
+use wildmatch::WildMatch;
 
 const DARCS_MISSING_MESSAGE: &str = "Darcs repository detected but the `darcs` CLI is not installed. Install it to enable Codex's Darcs integration.";
 
-static DARCS_WARNING_EMITTED: OnceLock<()> = OnceLock::new();
+/// Repository roots whose missing-CLI warning has already been logged.
+static DARCS_WARNED_ROOTS: LazyLock<Mutex<HashSet<PathBuf>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
 
 const DARCS_COMMAND_TIMEOUT: TokioDuration = TokioDuration::from_secs(5);
 
+/// Lines of darcs' stderr kept in the error for a failed command.
+const DARCS_STDERR_LINES: usize = 5;
+
+/// Environment variable naming the `darcs` executable to run instead of `darcs` from `PATH`.
+pub const DARCS_PATH_ENV_VAR: &str = "CODEX_DARCS_PATH";
+
+/// The `darcs` executable to invoke: `$CODEX_DARCS_PATH` when set, otherwise `darcs`
+/// resolved through `PATH`.
+pub fn darcs_program() -> OsString {
+    super::program_from_env(DARCS_PATH_ENV_VAR, "darcs")
+}
+
 /// Return the Darcs repository root if the provided directory is inside a Darcs
 /// checkout.
 ///
@@ -40,25 +61,36 @@ pub fn get_darcs_repo_root(base_dir: &Path) -> Option<PathBuf> {
     None
 }
 
-/// Returns `true` when the `darcs` executable is available on `PATH`.
+/// Returns `true` when the [`darcs_program`] executable can be found.
 pub fn darcs_cli_available() -> bool {
-    which::which("darcs").is_ok()
+    which::which(darcs_program()).is_ok()
 }
 
-/// Emit a warning (only once per process) when a Darcs repository is detected but the
-/// CLI is missing. The message is also returned so callers can surface it in the UI.
-pub fn warn_missing_darcs_cli() -> Option<String> {
+/// Emit a warning (once per repository root) when a Darcs repository is detected but
+/// the CLI is missing. The message is returned on every call so callers can surface it
+/// in the UI.
+pub fn warn_missing_darcs_cli(repo_root: &Path) -> Option<String> {
     if darcs_cli_available() {
         return None;
     }
 
-    if DARCS_WARNING_EMITTED.set(()).is_ok() {
-        warn!("{DARCS_MISSING_MESSAGE}");
-    }
-
+    warn_once_for_root(repo_root);
     Some(DARCS_MISSING_MESSAGE.to_string())
 }
 
+fn warn_once_for_root(repo_root: &Path) {
+    let first_for_root = DARCS_WARNED_ROOTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(repo_root.to_path_buf());
+    if first_for_root {
+        warn!(
+            "{DARCS_MISSING_MESSAGE} (repository: {})",
+            repo_root.display()
+        );
+    }
+}
+
 pub async fn collect_darcs_info(cwd: &Path) -> Option<DarcsInfo> {
     let repo_root = get_darcs_repo_root(cwd)?;
     if !darcs_cli_available() {
@@ -77,40 +109,201 @@ pub async fn collect_darcs_info(cwd: &Path) -> Option<DarcsInfo> {
         .or_else(|| extract_key_value(&text, "Current Branch"))
         .or_else(|| extract_key_value(&text, "Default branch"))
         .or_else(|| extract_key_value(&text, "Default Branch"));
-    let patch_hash = latest_patch_hash(&repo_root).await;
+    let (patch_hash, has_pending) = tokio::join!(
+        latest_patch_hash(&repo_root),
+        has_pending_changes(&repo_root)
+    );
 
     Some(DarcsInfo {
         patch_hash,
         branch,
         default_remote,
+        has_pending,
     })
 }
 
-pub async fn workspace_diff(cwd: &Path) -> io::Result<String> {
+/// Whether `darcs whatsnew --summary` reports unrecorded changes.
+async fn has_pending_changes(repo_root: &Path) -> Option<bool> {
+    let output = run_darcs_capture(repo_root, ["whatsnew", "--summary"])
+        .await
+        .ok()?;
+    pending_from_whatsnew(
+        output.status.code(),
+        &String::from_utf8_lossy(&output.stdout),
+    )
+}
+
+/// `whatsnew` exits 0 and lists the changes when there are any, and exits 1 when there
+/// are none; any other status is an error.
+fn pending_from_whatsnew(code: Option<i32>, stdout: &str) -> Option<bool> {
+    match code {
+        Some(0) => Some(!stdout.trim().is_empty()),
+        Some(1) => Some(false),
+        _ => None,
+    }
+}
+
+/// Return the pending changes of the Darcs workspace as reported by `darcs whatsnew`.
+///
+/// Unrecorded files are included via `--look-for-adds`, which honors the repository's
+/// boring file (`_darcs/prefs/boring`) but not Git-style ignore files.
+///
+/// Changes to paths matching any glob in `exclude` are dropped from the output. Globs are
+/// matched against repository-relative paths; a glob naming a directory also excludes
+/// everything below it.
+///
+/// `color` selects ANSI-colored output for display; pass `false` when the diff is consumed
+/// programmatically.
+pub async fn workspace_diff(
+    cwd: &Path,
+    exclude: &[String],
+    color: bool,
+) -> Result<String, RepoDiffError> {
     if get_darcs_repo_root(cwd).is_none() {
         return Ok(String::new());
     }
 
+    let color_arg = if color {
+        "--color=always"
+    } else {
+        "--color=never"
+    };
+    let args = ["whatsnew", "--unified", color_arg, "--look-for-adds"];
     let output = timeout(
         DARCS_COMMAND_TIMEOUT,
-        Command::new("darcs")
-            .args(["whatsnew", "--unified", "--color=always", "--look-for-adds"])
+        Command::new(darcs_program())
+            .args(args)
             .stdout(Stdio::piped())
-            .stderr(Stdio::null())
+            .stderr(Stdio::piped())
             .current_dir(cwd)
             .output(),
     )
     .await
-    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "darcs whatsnew timed out"))??;
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "darcs whatsnew timed out"))
+    .and_then(|output| output)
+    .map_err(RepoDiffError::Io)?;
+
+    if output.status.success() || output.status.code() == Some(1) {
+        let diff = String::from_utf8_lossy(&output.stdout);
+        if exclude.is_empty() {
+            Ok(diff.into_owned())
+        } else {
+            Ok(filter_excluded_changes(&diff, exclude))
+        }
+    } else {
+        Err(diff_command_failed(&args, &output))
+    }
+}
+
+/// Return `darcs whatsnew --summary` output for the workspace: one line per changed
+/// file with its status letter and, for text changes, `-removed +added` line counts.
+pub async fn workspace_summary(cwd: &Path) -> Result<String, RepoDiffError> {
+    if get_darcs_repo_root(cwd).is_none() {
+        return Ok(String::new());
+    }
 
+    let args = ["whatsnew", "--summary", "--look-for-adds"];
+    let output = run_darcs_capture(cwd, args)
+        .await
+        .map_err(RepoDiffError::Io)?;
+    // Like `whatsnew` in `workspace_diff`, exit status 1 means "no changes".
     if output.status.success() || output.status.code() == Some(1) {
         Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     } else {
-        Err(io::Error::other(format!(
-            "darcs whatsnew failed with status {}",
-            output.status
-        )))
+        Err(diff_command_failed(&args, &output))
+    }
+}
+
+/// Files recorded in the Darcs repository rooted at `repo_root`, relative to that root,
+/// as listed by `darcs show files`.
+pub async fn tracked_files(repo_root: &Path) -> io::Result<Vec<PathBuf>> {
+    let output = run_darcs_capture(repo_root, ["show", "files", "--no-directories"]).await?;
+    if !output.status.success() {
+        return Err(command_failed("darcs show files", &output));
+    }
+    Ok(parse_show_files(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Older darcs releases prefix every path with `./`; newer ones print them bare.
+fn parse_show_files(text: &str) -> Vec<PathBuf> {
+    text.lines()
+        .map(|line| line.strip_prefix("./").unwrap_or(line))
+        .filter(|path| !path.is_empty() && *path != ".")
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// A recorded Darcs patch, the Darcs counterpart of a Git commit log entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DarcsPatchEntry {
+    pub hash: String,
+    pub author: String,
+    /// Unix timestamp (seconds since epoch) at which the patch was recorded.
+    pub date: i64,
+    /// Single-line patch name.
+    pub name: String,
+}
+
+/// Return the last `limit` patches of the repository containing `cwd`, newest first,
+/// parsed from `darcs changes --xml`. Returns an empty vector when not in a Darcs
+/// repository, when the CLI is missing, or on error, timeout, or malformed output.
+pub async fn recent_patches(cwd: &Path, limit: usize) -> Vec<DarcsPatchEntry> {
+    let Some(repo_root) = get_darcs_repo_root(cwd) else {
+        return Vec::new();
+    };
+    if !darcs_cli_available() {
+        return Vec::new();
+    }
+
+    let last = format!("--last={}", limit.max(1));
+    let Ok(output) = run_darcs_capture(&repo_root, ["changes", last.as_str(), "--xml"]).await
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
     }
+    parse_changes_xml(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the `<patch>` elements of `darcs changes --xml` output. Patches missing a hash
+/// or with an unparseable date are skipped.
+fn parse_changes_xml(xml: &str) -> Vec<DarcsPatchEntry> {
+    xml.split("<patch ")
+        .skip(1)
+        .filter_map(|patch| {
+            let (tag, body) = patch.split_once('>')?;
+            let hash = find_attr_value(tag, "hash")?;
+            let author = find_attr_value(tag, "author").unwrap_or_default();
+            // Darcs records dates in UTC as `YYYYMMDDhhmmss`.
+            let date = chrono::NaiveDateTime::parse_from_str(
+                &find_attr_value(tag, "date")?,
+                "%Y%m%d%H%M%S",
+            )
+            .ok()?
+            .and_utc()
+            .timestamp();
+            let name = body
+                .split_once("<name>")
+                .and_then(|(_, rest)| rest.split_once("</name>"))
+                .map_or("", |(name, _)| name.trim());
+            Some(DarcsPatchEntry {
+                hash,
+                author: unescape_xml(&author),
+                date,
+                name: unescape_xml(name),
+            })
+        })
+        .collect()
+}
+
+/// Replace the predefined XML entities darcs uses in attribute values and text.
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
 }
 
 async fn latest_patch_hash(cwd: &Path) -> Option<String> {
@@ -131,6 +324,41 @@ async fn latest_patch_hash(cwd: &Path) -> Option<String> {
     extract_key_value(&text, "Patch hash")
 }
 
+/// Error for a darcs command that exited unsuccessfully, carrying its status and the
+/// first [`DARCS_STDERR_LINES`] lines of stderr (e.g. why a repository is unreadable).
+fn command_failed(command: &str, output: &std::process::Output) -> io::Error {
+    let stderr = stderr_excerpt(output);
+    if stderr.is_empty() {
+        io::Error::other(format!("{command} failed with status {}", output.status))
+    } else {
+        io::Error::other(format!(
+            "{command} failed with status {}: {stderr}",
+            output.status
+        ))
+    }
+}
+
+/// A [`RepoDiffError::CommandFailed`] for the diff command `darcs <args>`.
+fn diff_command_failed(args: &[&str], output: &std::process::Output) -> RepoDiffError {
+    RepoDiffError::CommandFailed {
+        backend: RevisionControlKind::Darcs,
+        command: format!("darcs {}", args.join(" ")),
+        status: output.status,
+        stderr: stderr_excerpt(output),
+    }
+}
+
+/// The first [`DARCS_STDERR_LINES`] non-empty lines of a command's stderr.
+fn stderr_excerpt(output: &std::process::Output) -> String {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .take(DARCS_STDERR_LINES)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 async fn run_darcs_capture<I, S>(cwd: &Path, args: I) -> io::Result<std::process::Output>
 where
     I: IntoIterator<Item = S>,
@@ -138,7 +366,7 @@ where
 {
     let output = timeout(
         DARCS_COMMAND_TIMEOUT,
-        Command::new("darcs")
+        Command::new(darcs_program())
             .args(args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -151,6 +379,63 @@ where
     Ok(output)
 }
 
+/// Primitive change headers emitted by `darcs whatsnew`, each followed by a `./path`.
+const DARCS_CHANGE_HEADERS: [&str; 8] = [
+    "hunk", "addfile", "rmfile", "adddir", "rmdir", "move", "binary", "replace",
+];
+
+/// Drop every change block in `diff` whose path matches one of the `exclude` globs.
+fn filter_excluded_changes(diff: &str, exclude: &[String]) -> String {
+    let patterns: Vec<(&str, WildMatch)> = exclude
+        .iter()
+        .map(|glob| (glob.trim_end_matches('/'), WildMatch::new(glob)))
+        .collect();
+    let mut filtered = String::with_capacity(diff.len());
+    let mut skipping = false;
+    for line in diff.split_inclusive('\n') {
+        if let Some(path) = change_header_path(line) {
+            skipping = patterns
+                .iter()
+                .any(|(dir, pattern)| pattern.matches(path) || is_within_dir(path, dir));
+        }
+        if !skipping {
+            filtered.push_str(line);
+        }
+    }
+    filtered
+}
+
+/// Return the repository-relative path named by a change header line, if `line` is one.
+fn change_header_path(line: &str) -> Option<&str> {
+    let plain = strip_ansi_prefix(line).trim_start();
+    let (keyword, rest) = plain.split_once(' ')?;
+    if !DARCS_CHANGE_HEADERS.contains(&keyword) {
+        return None;
+    }
+    let path = rest.split_whitespace().next()?.strip_prefix("./")?;
+    Some(strip_ansi_suffix(path))
+}
+
+fn is_within_dir(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn strip_ansi_prefix(line: &str) -> &str {
+    let mut rest = line;
+    while let Some(after_escape) = rest.strip_prefix("\u{1b}[") {
+        match after_escape.find('m') {
+            Some(end) => rest = &after_escape[end + 1..],
+            None => break,
+        }
+    }
+    rest
+}
+
+fn strip_ansi_suffix(token: &str) -> &str {
+    token.split('\u{1b}').next().unwrap_or(token)
+}
+
 fn extract_key_value(text: &str, key: &str) -> Option<String> {
     let key_lower = key.to_ascii_lowercase();
     for line in text.lines() {
@@ -174,7 +459,14 @@ fn extract_key_value(text: &str, key: &str) -> Option<String> {
 fn find_attr_value(text: &str, attr: &str) -> Option<String> {
     for quote in ['"', '\''] {
         let pattern = format!("{attr}={quote}");
-        if let Some(idx) = text.find(&pattern) {
+        // Skip matches inside a longer attribute name, e.g. `local_date` for `date`.
+        let found = text.match_indices(&pattern).find(|(idx, _)| {
+            text[..*idx]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace)
+        });
+        if let Some((idx, _)) = found {
             let rest = &text[idx + pattern.len()..];
             if let Some(end) = rest.find(quote) {
                 let value = rest[..end].trim();
@@ -191,6 +483,78 @@ fn find_attr_value(text: &str, attr: &str) -> Option<String> {
 mod tests {
     use super::*;
     use tempfile::tempdir;
+    use tracing_test::traced_test;
+
+    #[cfg(unix)]
+    #[test]
+    fn command_failed_includes_leading_stderr_lines() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let stderr: String = (1..=8).map(|line| format!("line {line}\n")).collect();
+        let output = std::process::Output {
+            status: std::process::ExitStatus::from_raw(2 << 8),
+            stdout: Vec::new(),
+            stderr: stderr.into_bytes(),
+        };
+
+        let message = command_failed("darcs whatsnew", &output).to_string();
+        assert_eq!(
+            message,
+            "darcs whatsnew failed with status exit status: 2: line 1\nline 2\nline 3\nline 4\nline 5"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn diff_command_failed_reports_command_status_and_stderr() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let output = std::process::Output {
+            status: std::process::ExitStatus::from_raw(2 << 8),
+            stdout: Vec::new(),
+            stderr: b"darcs: not a repository\n\n".to_vec(),
+        };
+
+        match diff_command_failed(&["whatsnew", "--summary"], &output) {
+            RepoDiffError::CommandFailed {
+                backend,
+                command,
+                status,
+                stderr,
+            } => {
+                assert_eq!(backend, RevisionControlKind::Darcs);
+                assert_eq!(command, "darcs whatsnew --summary");
+                assert_eq!(status.code(), Some(2));
+                assert_eq!(stderr, "darcs: not a repository");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    #[traced_test]
+    fn missing_cli_warning_is_logged_once_per_root() {
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+
+        for root in [first.path(), second.path(), first.path(), second.path()] {
+            warn_once_for_root(root);
+        }
+
+        logs_assert(|lines: &[&str]| {
+            for root in [first.path(), second.path()] {
+                let root = root.display().to_string();
+                let count = lines
+                    .iter()
+                    .filter(|line| line.contains(DARCS_MISSING_MESSAGE) && line.contains(&root))
+                    .count();
+                if count != 1 {
+                    return Err(format!("expected one warning for {root}, found {count}"));
+                }
+            }
+            Ok(())
+        });
+    }
 
     #[test]
     fn detects_nested_darcs_repository() {
@@ -203,6 +567,95 @@ mod tests {
         assert_eq!(get_darcs_repo_root(&subdir), Some(dir.path().to_path_buf()));
     }
 
+    #[test]
+    fn filter_excluded_changes_drops_matching_blocks() {
+        let diff = "\
+addfile ./Cargo.lock
+hunk ./Cargo.lock 1
++lock
+hunk ./src/main.rs 1
+-old
++new
+hunk ./vendor/dep.rs 4
++vendored
+";
+
+        let filtered = filter_excluded_changes(diff, &["*.lock".to_string(), "vendor".to_string()]);
+
+        assert_eq!(filtered, "hunk ./src/main.rs 1\n-old\n+new\n");
+    }
+
+    #[test]
+    fn pending_from_whatsnew_reads_exit_status() {
+        assert_eq!(
+            pending_from_whatsnew(Some(0), "M ./src/main.rs -1 +2\n"),
+            Some(true)
+        );
+        assert_eq!(pending_from_whatsnew(Some(0), ""), Some(false));
+        assert_eq!(pending_from_whatsnew(Some(1), "No changes!\n"), Some(false));
+        assert_eq!(pending_from_whatsnew(Some(2), ""), None);
+        assert_eq!(pending_from_whatsnew(None, ""), None);
+    }
+
+    #[test]
+    fn parse_show_files_strips_dot_prefix() {
+        assert_eq!(
+            parse_show_files(".\n./src/main.rs\nREADME.md\n\n"),
+            vec![PathBuf::from("src/main.rs"), PathBuf::from("README.md")]
+        );
+    }
+
+    #[test]
+    fn parse_changes_xml_reads_patches() {
+        let xml = "\
+<changelog>
+<patch author='Alice &lt;alice@example.com&gt;' local_date='Tue Jan  2 03:04:05 UTC 2024' date='20240102030405' inverted='False' hash='0001-first'>
+\t<name>Fix &amp; test parser</name>
+\t<comment>Ignore-this: 1234</comment>
+</patch>
+<patch author='bob' date='not-a-date' local_date='' inverted='False' hash='0002-bad'>
+\t<name>Skipped</name>
+</patch>
+<patch author='carol' date='19700101000010' local_date='' inverted='False' hash='0003-third'>
+\t<name>Initial import</name>
+</patch>
+</changelog>
+";
+
+        assert_eq!(
+            parse_changes_xml(xml),
+            vec![
+                DarcsPatchEntry {
+                    hash: "0001-first".to_string(),
+                    author: "Alice <alice@example.com>".to_string(),
+                    date: 1_704_164_645,
+                    name: "Fix & test parser".to_string(),
+                },
+                DarcsPatchEntry {
+                    hash: "0003-third".to_string(),
+                    author: "carol".to_string(),
+                    date: 10,
+                    name: "Initial import".to_string(),
+                },
+            ]
+        );
+        assert!(parse_changes_xml("not xml").is_empty());
+    }
+
+    #[tokio::test]
+    async fn workspace_diff_is_empty_outside_repository() {
+        let dir = tempdir().unwrap();
+        for color in [true, false] {
+            assert_eq!(workspace_diff(dir.path(), &[], color).await.unwrap(), "");
+        }
+    }
+
+    #[tokio::test]
+    async fn recent_patches_is_empty_outside_repository() {
+        let dir = tempdir().unwrap();
+        assert!(recent_patches(dir.path(), 10).await.is_empty());
+    }
+
     #[test]
     fn returns_none_for_non_repo() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,223 @@
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+use crate::git_info::RepoDiffError;
+use crate::revision_control::RevisionControlKind;
+use codex_protocol::protocol::FossilInfo;
+use tokio::process::Command;
+use tokio::time::Duration as TokioDuration;
+use tokio::time::timeout;
+use tracing::warn;
+
+const FOSSIL_MISSING_MESSAGE: &str = "Fossil checkout detected but the `fossil` CLI is not installed. Install it to enable Codex's Fossil integration.";
+
+static FOSSIL_WARNING_EMITTED: OnceLock<()> = OnceLock::new();
+
+const FOSSIL_COMMAND_TIMEOUT: TokioDuration = TokioDuration::from_secs(5);
+
+/// Environment variable naming the `fossil` executable to run instead of `fossil` from `PATH`.
+pub const FOSSIL_PATH_ENV_VAR: &str = "CODEX_FOSSIL_PATH";
+
+/// The `fossil` executable to invoke: `$CODEX_FOSSIL_PATH` when set, otherwise `fossil`
+/// resolved through `PATH`.
+pub fn fossil_program() -> OsString {
+    super::program_from_env(FOSSIL_PATH_ENV_VAR, "fossil")
+}
+
+/// Checkout database names Fossil creates at the root of an open checkout.
+/// `_FOSSIL_` is the legacy name still used on some platforms.
+pub(super) const FOSSIL_CHECKOUT_MARKERS: [&str; 2] = [".fslckout", "_FOSSIL_"];
+
+/// Return the Fossil checkout root if the provided directory is inside an open
+/// Fossil checkout.
+///
+/// Like the Git and Darcs implementations this walks up the directory tree,
+/// looking for the checkout database file (`.fslckout` or `_FOSSIL_`) so that
+/// detection does not require the `fossil` binary.
+pub fn get_fossil_repo_root(base_dir: &Path) -> Option<PathBuf> {
+    let mut dir = base_dir.to_path_buf();
+
+    loop {
+        if FOSSIL_CHECKOUT_MARKERS
+            .iter()
+            .any(|marker| dir.join(marker).is_file())
+        {
+            return Some(dir);
+        }
+
+        if !dir.pop() {
+            break;
+        }
+    }
+
+    None
+}
+
+/// Returns `true` when the [`fossil_program`] executable can be found.
+pub fn fossil_cli_available() -> bool {
+    which::which(fossil_program()).is_ok()
+}
+
+/// Emit a warning (only once per process) when a Fossil checkout is detected but the
+/// CLI is missing. The message is also returned so callers can surface it in the UI.
+pub fn warn_missing_fossil_cli() -> Option<String> {
+    if fossil_cli_available() {
+        return None;
+    }
+
+    if FOSSIL_WARNING_EMITTED.set(()).is_ok() {
+        warn!("{FOSSIL_MISSING_MESSAGE}");
+    }
+
+    Some(FOSSIL_MISSING_MESSAGE.to_string())
+}
+
+/// Collect the checkout hash and branch from `fossil status`.
+pub async fn collect_fossil_info(cwd: &Path) -> Option<FossilInfo> {
+    let repo_root = get_fossil_repo_root(cwd)?;
+    if !fossil_cli_available() {
+        return None;
+    }
+
+    let output = run_fossil_capture(&repo_root, ["status"]).await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(parse_fossil_status(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Return the uncommitted changes of the Fossil checkout as a unified diff.
+pub async fn workspace_diff(cwd: &Path) -> Result<String, RepoDiffError> {
+    if get_fossil_repo_root(cwd).is_none() {
+        return Ok(String::new());
+    }
+
+    let output = run_fossil_capture(cwd, ["diff", "--unified"])
+        .await
+        .map_err(RepoDiffError::Io)?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(RepoDiffError::CommandFailed {
+            backend: RevisionControlKind::Fossil,
+            command: "fossil diff --unified".to_string(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+async fn run_fossil_capture<I, S>(cwd: &Path, args: I) -> io::Result<std::process::Output>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = timeout(
+        FOSSIL_COMMAND_TIMEOUT,
+        Command::new(fossil_program())
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .current_dir(cwd)
+            .output(),
+    )
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "fossil command timed out"))??;
+
+    Ok(output)
+}
+
+/// Parse the `checkout:` and `tags:` lines of `fossil status` output.
+///
+/// The checkout line carries the hash followed by a timestamp; the first tag is the
+/// branch name (`trunk` unless the checkout is on another branch).
+fn parse_fossil_status(text: &str) -> FossilInfo {
+    let checkout_hash = status_field(text, "checkout")
+        .and_then(|value| value.split_whitespace().next())
+        .map(str::to_string);
+    let branch = status_field(text, "tags")
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string);
+
+    FossilInfo {
+        checkout_hash,
+        branch,
+    }
+}
+
+fn status_field<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    text.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    #[test]
+    fn detects_nested_fossil_checkout() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join(".fslckout"), "").unwrap();
+
+        let subdir = dir.path().join("nested");
+        std::fs::create_dir(&subdir).unwrap();
+
+        assert_eq!(
+            get_fossil_repo_root(&subdir),
+            Some(dir.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn detects_legacy_checkout_marker() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("_FOSSIL_"), "").unwrap();
+
+        assert_eq!(
+            get_fossil_repo_root(dir.path()),
+            Some(dir.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn parses_fossil_status() {
+        let status = "\
+repository:   /home/user/repos/project.fossil
+local-root:   /home/user/src/project/
+config-db:    /home/user/.config/fossil.db
+checkout:     5b3c7e1f0a2d4c6e8f9a1b2c3d4e5f60718293a4 2025-01-02 03:04:05 UTC
+parent:       0a1b2c3d4e5f60718293a4b5c6d7e8f9a0b1c2d3 2025-01-01 00:00:00 UTC
+tags:         feature-x, release
+comment:      Add the thing (user: alice)
+EDITED     src/main.c
+";
+
+        assert_eq!(
+            parse_fossil_status(status),
+            FossilInfo {
+                checkout_hash: Some("5b3c7e1f0a2d4c6e8f9a1b2c3d4e5f60718293a4".to_string()),
+                branch: Some("feature-x".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn returns_none_for_non_checkout() {
+        let dir = tempdir().unwrap();
+        assert!(get_fossil_repo_root(dir.path()).is_none());
+    }
+}
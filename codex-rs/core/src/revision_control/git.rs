@@ -1,6 +1,17 @@
+use std::env;
+use std::ffi::OsString;
 use std::path::Path;
 use std::path::PathBuf;
 
+/// Environment variable naming the `git` executable to run instead of `git` from `PATH`.
+pub const GIT_PATH_ENV_VAR: &str = "CODEX_GIT_PATH";
+
+/// The `git` executable every git invocation should use: `$CODEX_GIT_PATH` when set,
+/// otherwise `git` resolved through `PATH`.
+pub fn git_program() -> OsString {
+    super::program_from_env(GIT_PATH_ENV_VAR, "git")
+}
+
 /// Return `true` if the project folder specified by the `Config` is inside a
 /// Git repository.
 ///
@@ -13,6 +24,10 @@ use std::path::PathBuf;
 /// `git worktree add` where the checkout lives outside the main repository
 /// directory. If you need Codex to work from such a checkout simply pass the
 /// `--allow-no-git-exec` CLI flag that disables the repo requirement.
+///
+/// When the walk finds nothing but `GIT_DIR` is set (optionally with
+/// `GIT_WORK_TREE`), the repository lives outside the directory tree, so git
+/// itself is asked for the top level instead.
 pub fn get_git_repo_root(base_dir: &Path) -> Option<PathBuf> {
     let mut dir = base_dir.to_path_buf();
 
@@ -26,9 +41,80 @@ pub fn get_git_repo_root(base_dir: &Path) -> Option<PathBuf> {
         }
     }
 
+    repo_root_from_git_env(
+        base_dir,
+        env::var_os("GIT_DIR")?,
+        env::var_os("GIT_WORK_TREE"),
+    )
+}
+
+/// Top level of the working tree git uses from `base_dir` with the given `GIT_DIR` and
+/// `GIT_WORK_TREE`, via `git rev-parse --show-toplevel`.
+fn repo_root_from_git_env(
+    base_dir: &Path,
+    git_dir: OsString,
+    work_tree: Option<OsString>,
+) -> Option<PathBuf> {
+    let mut command = std::process::Command::new(git_program());
+    command
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(base_dir)
+        .env("GIT_DIR", git_dir);
+    match work_tree {
+        Some(work_tree) => command.env("GIT_WORK_TREE", work_tree),
+        None => command.env_remove("GIT_WORK_TREE"),
+    };
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let toplevel = String::from_utf8(output.stdout).ok()?;
+    let toplevel = toplevel.trim();
+    (!toplevel.is_empty()).then(|| PathBuf::from(toplevel))
+}
+
+/// Return the directory of the bare Git repository containing `base_dir`, if any.
+///
+/// Bare repositories have no `.git` entry, so [`get_git_repo_root`] never finds
+/// them. Candidates are found by their on-disk layout (`HEAD`, `objects/`,
+/// `refs/`) and confirmed with `git rev-parse --is-bare-repository`.
+pub fn get_bare_git_repo_root(base_dir: &Path) -> Option<PathBuf> {
+    let mut dir = base_dir.to_path_buf();
+
+    loop {
+        if dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir() {
+            let output = std::process::Command::new(git_program())
+                .args(["rev-parse", "--is-bare-repository"])
+                .current_dir(&dir)
+                .output()
+                .ok()?;
+            if output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true" {
+                return Some(dir);
+            }
+        }
+
+        if !dir.pop() {
+            break;
+        }
+    }
+
     None
 }
 
+/// Return `true` if the repository's top-level `.gitattributes` routes any paths
+/// through the Git LFS filter. Such paths appear as pointer files in diffs and
+/// snapshots rather than their real contents.
+pub fn uses_lfs(repo_root: &Path) -> bool {
+    let Ok(attributes) = std::fs::read_to_string(repo_root.join(".gitattributes")) else {
+        return false;
+    };
+    attributes
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .any(|line| line.split_whitespace().any(|attr| attr == "filter=lfs"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,6 +131,56 @@ mod tests {
         assert_eq!(get_git_repo_root(&subdir), Some(dir.path().to_path_buf()));
     }
 
+    #[test]
+    fn detects_lfs_filter_in_gitattributes() {
+        let dir = tempdir().unwrap();
+        assert!(!uses_lfs(dir.path()));
+
+        std::fs::write(
+            dir.path().join(".gitattributes"),
+            "# *.bin filter=lfs\n*.txt text eol=lf\n",
+        )
+        .unwrap();
+        assert!(!uses_lfs(dir.path()));
+
+        std::fs::write(
+            dir.path().join(".gitattributes"),
+            "*.txt text eol=lf\n*.psd filter=lfs diff=lfs merge=lfs -text\n",
+        )
+        .unwrap();
+        assert!(uses_lfs(dir.path()));
+    }
+
+    #[test]
+    fn resolves_root_from_git_dir_and_work_tree() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let work_tree = root.join("work");
+        let nested = work_tree.join("nested");
+        std::fs::create_dir_all(&nested).unwrap();
+        let status = std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(&work_tree)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let git_dir = root.join("external.git");
+        std::fs::rename(work_tree.join(".git"), &git_dir).unwrap();
+
+        assert_eq!(
+            repo_root_from_git_env(
+                &nested,
+                git_dir.clone().into_os_string(),
+                Some(work_tree.clone().into_os_string()),
+            ),
+            Some(work_tree)
+        );
+        assert_eq!(
+            repo_root_from_git_env(&nested, root.join("missing.git").into_os_string(), None),
+            None
+        );
+    }
+
     #[test]
     fn returns_none_for_non_repo() {
         let dir = tempdir().unwrap();
@@ -1,17 +1,27 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::io;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use crate::git_info;
+use async_trait::async_trait;
 use codex_protocol::protocol::RevisionControlBackend;
 use codex_protocol::protocol::RevisionControlSummary;
+use std::collections::HashMap;
+use thiserror::Error;
 
 pub mod darcs;
+pub mod fossil;
 pub mod git;
 
 /// Enumeration of revision control backends supported by Codex.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum RevisionControlKind {
     Git,
+    Fossil,
     Darcs,
 }
 
@@ -20,16 +30,47 @@ impl RevisionControlKind {
     pub fn display_name(&self) -> &'static str {
         match self {
             Self::Git => "Git",
+            Self::Fossil => "Fossil",
             Self::Darcs => "Darcs",
         }
     }
 }
 
+impl fmt::Display for RevisionControlKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.display_name())
+    }
+}
+
+/// Error returned when parsing an unknown backend name into a [`RevisionControlKind`].
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unknown revision control backend `{0}`; expected one of: git, fossil, darcs")]
+pub struct ParseRevisionControlKindError(pub String);
+
+impl FromStr for RevisionControlKind {
+    type Err = ParseRevisionControlKindError;
+
+    /// Parse a backend name such as `git` or `Darcs`, ignoring case.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "git" => Ok(Self::Git),
+            "fossil" => Ok(Self::Fossil),
+            "darcs" => Ok(Self::Darcs),
+            _ => Err(ParseRevisionControlKindError(value.to_string())),
+        }
+    }
+}
+
 /// Capabilities supported by the detected revision control backend.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct RevisionControlCapabilities {
     pub supports_diffs: bool,
     pub supports_snapshots: bool,
+    /// Files are stored through Git LFS, so diffs and snapshots only see pointer files.
+    pub supports_lfs: bool,
+    /// Individual paths can be restored from a snapshot without touching the rest of the
+    /// working tree.
+    pub supports_partial_restore: bool,
 }
 
 impl RevisionControlCapabilities {
@@ -37,17 +78,30 @@ impl RevisionControlCapabilities {
         Self {
             supports_diffs,
             supports_snapshots,
+            supports_lfs: false,
+            supports_partial_restore: false,
         }
     }
 
+    pub const fn with_lfs(mut self, supports_lfs: bool) -> Self {
+        self.supports_lfs = supports_lfs;
+        self
+    }
+
+    pub const fn with_partial_restore(mut self, supports_partial_restore: bool) -> Self {
+        self.supports_partial_restore = supports_partial_restore;
+        self
+    }
+
     const fn for_kind(kind: RevisionControlKind) -> Self {
         match kind {
-            RevisionControlKind::Git => Self::new(true, true),
-            RevisionControlKind::Darcs => Self::new(true, false),
+            RevisionControlKind::Git => Self::new(true, true).with_partial_restore(true),
+            RevisionControlKind::Fossil | RevisionControlKind::Darcs => Self::new(true, false),
         }
     }
 }
 
+#[async_trait]
 pub trait RevisionControlSystem: Send + Sync {
     fn kind(&self) -> RevisionControlKind;
     fn root(&self) -> &Path;
@@ -60,6 +114,73 @@ pub trait RevisionControlSystem: Send + Sync {
     fn tooling_error(&self) -> Option<&str> {
         None
     }
+
+    /// Whether the repository has no working tree (a bare Git repository).
+    fn is_bare(&self) -> bool {
+        false
+    }
+
+    /// Directory whose trust decision covers `cwd`. Git resolves worktrees to their main
+    /// repository so every worktree shares one decision; other backends use [`Self::root`].
+    fn trust_root(&self, cwd: &Path) -> Option<PathBuf> {
+        match self.kind() {
+            RevisionControlKind::Git => git_info::resolve_root_git_project_for_trust(cwd),
+            RevisionControlKind::Fossil | RevisionControlKind::Darcs => {
+                Some(self.root().to_path_buf())
+            }
+        }
+    }
+
+    /// Files under version control, relative to [`Self::root`]: `git ls-files` for Git and
+    /// `darcs show files` for Darcs. Fossil checkouts are not supported yet.
+    async fn tracked_files(&self) -> io::Result<Vec<PathBuf>> {
+        match self.kind() {
+            RevisionControlKind::Git => git_info::tracked_files(self.root()).await,
+            RevisionControlKind::Darcs => darcs::tracked_files(self.root()).await,
+            RevisionControlKind::Fossil => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "listing tracked files is not supported for Fossil checkouts",
+            )),
+        }
+    }
+
+    /// Whether `path` (absolute, or relative to `cwd`) is under version control; for a
+    /// directory, whether it holds any tracked file. Paths outside [`Self::root`] are
+    /// never tracked. Fossil checkouts are not supported yet.
+    async fn is_tracked(&self, cwd: &Path, path: &Path) -> io::Result<bool> {
+        let Some(relative) = path_within_root(self.root(), &cwd.join(path)) else {
+            return Ok(false);
+        };
+        match self.kind() {
+            RevisionControlKind::Git => git_info::is_tracked(self.root(), &relative).await,
+            RevisionControlKind::Darcs => Ok(darcs::tracked_files(self.root())
+                .await?
+                .iter()
+                .any(|file| file.starts_with(&relative))),
+            RevisionControlKind::Fossil => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "checking tracked files is not supported for Fossil checkouts",
+            )),
+        }
+    }
+}
+
+/// `path` relative to `root` after resolving `.` and `..` lexically, or `None` when it
+/// lies outside `root`.
+fn path_within_root(root: &Path, path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized.strip_prefix(root).ok().map(Path::to_path_buf)
 }
 
 /// Information about the detected revision control system for a workspace.
@@ -69,6 +190,9 @@ pub struct DetectedRevisionControl {
     pub root: PathBuf,
     pub capabilities: RevisionControlCapabilities,
     pub tooling_error: Option<String>,
+    /// Bare Git repository without a working tree; diffs are empty and snapshots
+    /// are unavailable.
+    pub is_bare: bool,
 }
 
 impl DetectedRevisionControl {
@@ -81,12 +205,25 @@ impl DetectedRevisionControl {
         root: PathBuf,
         tooling_error: Option<String>,
     ) -> Self {
-        let capabilities = RevisionControlCapabilities::for_kind(kind);
+        let capabilities = RevisionControlCapabilities::for_kind(kind)
+            .with_lfs(kind == RevisionControlKind::Git && git::uses_lfs(&root));
         Self {
             kind,
             root,
             capabilities,
             tooling_error,
+            is_bare: false,
+        }
+    }
+
+    /// Detection result for a bare Git repository rooted at `root`.
+    pub fn new_bare_git(root: PathBuf) -> Self {
+        Self {
+            kind: RevisionControlKind::Git,
+            root,
+            capabilities: RevisionControlCapabilities::new(true, false),
+            tooling_error: None,
+            is_bare: true,
         }
     }
 }
@@ -107,31 +244,183 @@ impl RevisionControlSystem for DetectedRevisionControl {
     fn tooling_error(&self) -> Option<&str> {
         self.tooling_error.as_deref()
     }
+
+    fn is_bare(&self) -> bool {
+        self.is_bare
+    }
+}
+
+/// Memoized [`detect_revision_control`] results keyed by canonicalized `base_dir`, for
+/// long-lived callers such as the TUI that detect the same directory repeatedly.
+///
+/// Only where the repository is gets cached: a hit skips the filesystem walk but still
+/// recomputes capabilities and the missing-CLI error, so installing a CLI or enabling
+/// LFS mid-session is picked up. Only successful detections are cached, and an entry is
+/// dropped once its root's marker (`.git`, `.fslckout`, `_darcs`, ...) disappears.
+#[derive(Debug, Default)]
+pub struct DetectionCache {
+    entries: HashMap<PathBuf, RepositoryLocation>,
+}
+
+impl DetectionCache {
+    /// Cached counterpart of [`detect_revision_control`]. Paths that cannot be
+    /// canonicalized are detected without caching.
+    pub fn detect(&mut self, base_dir: &Path) -> Option<DetectedRevisionControl> {
+        let Ok(key) = base_dir.canonicalize() else {
+            return detect_revision_control(base_dir);
+        };
+        if let Some(cached) = self.entries.get(&key) {
+            if cached.marker_exists() {
+                return Some(cached.clone().detected());
+            }
+            self.entries.remove(&key);
+        }
+
+        let location = locate_repository(base_dir)?;
+        self.entries.insert(key, location.clone());
+        Some(location.detected())
+    }
+
+    /// Forget every cached detection result, e.g. after the user creates or removes a
+    /// repository.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
 }
 
 /// Attempt to detect the revision control backend rooted at `base_dir`.
+///
+/// When repositories are nested (e.g. a Darcs checkout vendored inside a Git
+/// repository), the innermost one containing `base_dir` wins. If a single
+/// directory holds several repositories, Git takes precedence, then Fossil,
+/// then Darcs. Bare Git repositories are only considered when no other
+/// repository is found.
+///
+/// Callers that detect the same directory repeatedly can go through a
+/// [`DetectionCache`] instead.
 pub fn detect_revision_control(base_dir: &Path) -> Option<DetectedRevisionControl> {
-    if let Some(root) = git::get_git_repo_root(base_dir) {
-        return Some(DetectedRevisionControl::new(RevisionControlKind::Git, root));
+    locate_repository(base_dir).map(RepositoryLocation::detected)
+}
+
+/// The repository found for a directory, before capabilities and tooling are checked.
+#[derive(Clone, Debug)]
+struct RepositoryLocation {
+    kind: RevisionControlKind,
+    root: PathBuf,
+    is_bare: bool,
+}
+
+impl RepositoryLocation {
+    fn detected(self) -> DetectedRevisionControl {
+        match self.kind {
+            RevisionControlKind::Git if self.is_bare => {
+                DetectedRevisionControl::new_bare_git(self.root)
+            }
+            RevisionControlKind::Git => {
+                DetectedRevisionControl::new(RevisionControlKind::Git, self.root)
+            }
+            RevisionControlKind::Fossil => detected_fossil(self.root),
+            RevisionControlKind::Darcs => detected_darcs(self.root),
+        }
+    }
+
+    /// Whether the marker that identified `root` as a repository is still there.
+    fn marker_exists(&self) -> bool {
+        let root = &self.root;
+        match self.kind {
+            RevisionControlKind::Git if self.is_bare => root.join("HEAD").is_file(),
+            RevisionControlKind::Git => root.join(".git").exists(),
+            RevisionControlKind::Fossil => fossil::FOSSIL_CHECKOUT_MARKERS
+                .iter()
+                .any(|marker| root.join(marker).is_file()),
+            RevisionControlKind::Darcs => root.join("_darcs").exists(),
+        }
     }
+}
 
-    darcs::get_darcs_repo_root(base_dir).map(|root| {
-        let tooling_error = darcs::warn_missing_darcs_cli();
-        DetectedRevisionControl::new_with_tooling_error(
+fn locate_repository(base_dir: &Path) -> Option<RepositoryLocation> {
+    // Candidates in precedence order; all roots are ancestors of `base_dir`, so the
+    // deepest path is the innermost repository.
+    let candidates = [
+        (RevisionControlKind::Git, git::get_git_repo_root(base_dir)),
+        (
+            RevisionControlKind::Fossil,
+            fossil::get_fossil_repo_root(base_dir),
+        ),
+        (
             RevisionControlKind::Darcs,
+            darcs::get_darcs_repo_root(base_dir),
+        ),
+    ];
+    let mut innermost: Option<(RevisionControlKind, PathBuf)> = None;
+    for (kind, root) in candidates {
+        let Some(root) = root else {
+            continue;
+        };
+        let deeper = innermost
+            .as_ref()
+            .is_none_or(|(_, current)| root.components().count() > current.components().count());
+        if deeper {
+            innermost = Some((kind, root));
+        }
+    }
+
+    match innermost {
+        Some((kind, root)) => Some(RepositoryLocation {
+            kind,
             root,
-            tooling_error,
-        )
-    })
+            is_bare: false,
+        }),
+        None => git::get_bare_git_repo_root(base_dir).map(|root| RepositoryLocation {
+            kind: RevisionControlKind::Git,
+            root,
+            is_bare: true,
+        }),
+    }
+}
+
+fn detected_fossil(root: PathBuf) -> DetectedRevisionControl {
+    let tooling_error = fossil::warn_missing_fossil_cli();
+    DetectedRevisionControl::new_with_tooling_error(
+        RevisionControlKind::Fossil,
+        root,
+        tooling_error,
+    )
+}
+
+fn detected_darcs(root: PathBuf) -> DetectedRevisionControl {
+    let tooling_error = darcs::warn_missing_darcs_cli(&root);
+    DetectedRevisionControl::new_with_tooling_error(RevisionControlKind::Darcs, root, tooling_error)
 }
 
+/// Async counterpart of [`detect_revision_control`].
+///
+/// Detection walks the filesystem and may probe `PATH` for the Fossil or Darcs CLI, so it
+/// runs on the blocking thread pool instead of the async reactor.
+pub async fn detect_revision_control_async(base_dir: &Path) -> Option<DetectedRevisionControl> {
+    let base_dir = base_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || detect_revision_control(&base_dir))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Status information for the repository at `cwd`. With `include_diff`, the
+/// backend's diff of uncommitted changes is captured in the same pass as
+/// [`RevisionControlSummary::workspace_diff`].
 pub async fn collect_revision_control_summary(
     backend: &dyn RevisionControlSystem,
     cwd: &Path,
+    include_diff: bool,
 ) -> Option<RevisionControlSummary> {
     let tooling_error = backend
         .tooling_error()
         .map(std::string::ToString::to_string);
+    let workspace_diff = if include_diff {
+        workspace_diff(backend, cwd).await
+    } else {
+        None
+    };
 
     match backend.kind() {
         RevisionControlKind::Git => {
@@ -139,8 +428,21 @@ pub async fn collect_revision_control_summary(
             Some(RevisionControlSummary {
                 kind: RevisionControlBackend::Git,
                 git: git_info,
+                fossil: None,
+                darcs: None,
+                tooling_error,
+                workspace_diff,
+            })
+        }
+        RevisionControlKind::Fossil => {
+            let fossil_info = fossil::collect_fossil_info(cwd).await;
+            Some(RevisionControlSummary {
+                kind: RevisionControlBackend::Fossil,
+                git: None,
+                fossil: fossil_info,
                 darcs: None,
                 tooling_error,
+                workspace_diff,
             })
         }
         RevisionControlKind::Darcs => {
@@ -148,36 +450,82 @@ pub async fn collect_revision_control_summary(
             Some(RevisionControlSummary {
                 kind: RevisionControlBackend::Darcs,
                 git: None,
+                fossil: None,
                 darcs: darcs_info,
                 tooling_error,
+                workspace_diff,
             })
         }
     }
 }
 
+/// Diff of uncommitted changes from the backend's CLI; `None` for bare repositories
+/// or when the diff command fails.
+async fn workspace_diff(backend: &dyn RevisionControlSystem, cwd: &Path) -> Option<String> {
+    match backend.kind() {
+        RevisionControlKind::Git if backend.is_bare() => None,
+        RevisionControlKind::Git => git_info::workspace_diff(cwd).await,
+        RevisionControlKind::Fossil => fossil::workspace_diff(cwd).await.ok(),
+        RevisionControlKind::Darcs => darcs::workspace_diff(cwd, &[], false).await.ok(),
+    }
+}
+
+/// Trust root for `base_dir`, using `detected` when the caller already ran detection.
+/// See [`RevisionControlSystem::trust_root`].
 pub fn resolve_revision_control_project_for_trust(
     base_dir: &Path,
     detected: Option<&DetectedRevisionControl>,
 ) -> Option<PathBuf> {
-    let detected = match detected {
-        Some(info) => info.clone(),
-        None => detect_revision_control(base_dir)?,
-    };
-
-    match detected.kind {
-        RevisionControlKind::Git => git_info::resolve_root_git_project_for_trust(base_dir),
-        RevisionControlKind::Darcs => Some(detected.root),
+    match detected {
+        Some(detected) => detected.trust_root(base_dir),
+        None => detect_revision_control(base_dir)?.trust_root(base_dir),
     }
 }
 
 pub use git::get_git_repo_root;
 
+/// Program named by the environment variable `var`, or `default` when it is unset or
+/// empty. Lets locked-down machines point Codex at a VCS binary that is not on `PATH`.
+pub(crate) fn program_from_env(var: &str, default: &str) -> OsString {
+    program_or_default(std::env::var_os(var), default)
+}
+
+fn program_or_default(value: Option<OsString>, default: &str) -> OsString {
+    value
+        .filter(|program| !program.is_empty())
+        .unwrap_or_else(|| OsString::from(default))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::tempdir;
 
+    #[test]
+    fn kind_round_trips_through_display_and_from_str() {
+        for kind in [
+            RevisionControlKind::Git,
+            RevisionControlKind::Fossil,
+            RevisionControlKind::Darcs,
+        ] {
+            assert_eq!(kind.to_string().parse::<RevisionControlKind>(), Ok(kind));
+        }
+        assert_eq!("GIT".parse(), Ok(RevisionControlKind::Git));
+        assert_eq!("darcs".parse(), Ok(RevisionControlKind::Darcs));
+    }
+
+    #[test]
+    fn unknown_kind_fails_to_parse() {
+        let err = "svn".parse::<RevisionControlKind>().unwrap_err();
+
+        assert_eq!(err, ParseRevisionControlKindError("svn".to_string()));
+        assert_eq!(
+            err.to_string(),
+            "unknown revision control backend `svn`; expected one of: git, fossil, darcs"
+        );
+    }
+
     #[test]
     fn detects_git_repository() {
         let dir = tempdir().unwrap();
@@ -192,11 +540,177 @@ mod tests {
         assert_eq!(detected.root, dir.path());
         assert_eq!(
             detected.capabilities,
-            RevisionControlCapabilities::new(true, true)
+            RevisionControlCapabilities::new(true, true).with_partial_restore(true)
         );
+        assert!(detected.capabilities.supports_partial_restore);
         assert!(detected.tooling_error.is_none());
     }
 
+    #[test]
+    fn detects_git_lfs_capability() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(
+            dir.path().join(".gitattributes"),
+            "*.bin filter=lfs diff=lfs merge=lfs -text\n",
+        )
+        .unwrap();
+
+        let detected = detect_revision_control(dir.path()).unwrap();
+
+        assert_eq!(
+            detected.capabilities,
+            RevisionControlCapabilities::new(true, true)
+                .with_lfs(true)
+                .with_partial_restore(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn async_detection_matches_sync_detection() {
+        let dir = tempdir().unwrap();
+        assert_eq!(detect_revision_control_async(dir.path()).await, None);
+
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+
+        assert_eq!(
+            detect_revision_control_async(&nested).await,
+            detect_revision_control(&nested)
+        );
+    }
+
+    #[test]
+    fn detects_bare_git_repository() {
+        let dir = tempdir().unwrap();
+        let status = std::process::Command::new("git")
+            .args(["init", "--bare"])
+            .current_dir(dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let detected = detect_revision_control(&dir.path().join("refs")).unwrap();
+
+        assert_eq!(
+            detected,
+            DetectedRevisionControl::new_bare_git(dir.path().to_path_buf())
+        );
+        assert_eq!(
+            detected.capabilities,
+            RevisionControlCapabilities::new(true, false)
+        );
+    }
+
+    #[tokio::test]
+    async fn tracked_files_lists_git_index_relative_to_root() {
+        let dir = tempdir().unwrap();
+        let run_git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        run_git(&["init", "--initial-branch", "main"]);
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("README.md"), "readme").unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "lib").unwrap();
+        fs::write(dir.path().join("untracked.txt"), "new").unwrap();
+        run_git(&["add", "README.md", "src/lib.rs"]);
+
+        let detected = detect_revision_control(&dir.path().join("src")).unwrap();
+        let files = detected.tracked_files().await.unwrap();
+
+        assert_eq!(
+            files,
+            vec![PathBuf::from("README.md"), PathBuf::from("src/lib.rs")]
+        );
+    }
+
+    #[tokio::test]
+    async fn is_tracked_checks_git_index_and_rejects_outside_paths() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join("src")).unwrap();
+        let status = std::process::Command::new("git")
+            .args(["init", "--initial-branch", "main"])
+            .current_dir(&repo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        fs::write(repo.join("src/lib.rs"), "lib").unwrap();
+        fs::write(repo.join("untracked.txt"), "new").unwrap();
+        fs::write(dir.path().join("outside.txt"), "outside").unwrap();
+        let status = std::process::Command::new("git")
+            .args(["add", "src/lib.rs"])
+            .current_dir(&repo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let detected = detect_revision_control(&repo).unwrap();
+        let src = repo.join("src");
+
+        assert!(
+            detected
+                .is_tracked(&src, Path::new("lib.rs"))
+                .await
+                .unwrap()
+        );
+        assert!(detected.is_tracked(&repo, Path::new("src")).await.unwrap());
+        assert!(
+            !detected
+                .is_tracked(&repo, Path::new("untracked.txt"))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !detected
+                .is_tracked(&src, Path::new("../../outside.txt"))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !detected
+                .is_tracked(&repo, &dir.path().join("outside.txt"))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn summary_includes_workspace_diff_on_request() {
+        let dir = tempdir().unwrap();
+        let run_git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        run_git(&["init", "--initial-branch", "main"]);
+        fs::write(dir.path().join("README.md"), "readme\n").unwrap();
+        fs::write(dir.path().join("untracked.txt"), "new\n").unwrap();
+        run_git(&["add", "README.md"]);
+        let detected = detect_revision_control(dir.path()).unwrap();
+
+        let summary = collect_revision_control_summary(&detected, dir.path(), false)
+            .await
+            .unwrap();
+        assert_eq!(summary.workspace_diff, None);
+
+        let summary = collect_revision_control_summary(&detected, dir.path(), true)
+            .await
+            .unwrap();
+        let diff = summary.workspace_diff.unwrap();
+        assert!(diff.contains("+readme"));
+        assert!(diff.contains("+new"));
+    }
+
     #[test]
     fn returns_none_when_no_repo_found() {
         let dir = tempdir().unwrap();
@@ -227,6 +741,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn detects_fossil_checkout() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(".fslckout"), "").unwrap();
+
+        let detected = detect_revision_control(dir.path()).unwrap();
+
+        assert_eq!(detected.kind, RevisionControlKind::Fossil);
+        assert_eq!(detected.root, dir.path());
+        assert_eq!(
+            detected.capabilities,
+            RevisionControlCapabilities::new(true, false)
+        );
+        assert_eq!(
+            detected.tooling_error.is_some(),
+            !fossil::fossil_cli_available()
+        );
+    }
+
+    #[test]
+    fn prefers_git_then_fossil_then_darcs_in_same_directory() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("_darcs")).unwrap();
+        fs::write(dir.path().join("_FOSSIL_"), "").unwrap();
+
+        let detected = detect_revision_control(dir.path()).unwrap();
+        assert_eq!(detected.kind, RevisionControlKind::Fossil);
+
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        let detected = detect_revision_control(dir.path()).unwrap();
+        assert_eq!(detected.kind, RevisionControlKind::Git);
+    }
+
+    #[test]
+    fn detects_innermost_repository() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        let vendored = dir.path().join("vendor").join("dep");
+        fs::create_dir_all(vendored.join("_darcs")).unwrap();
+        let cwd = vendored.join("src");
+        fs::create_dir(&cwd).unwrap();
+
+        let detected = detect_revision_control(&cwd).unwrap();
+        assert_eq!(detected.kind, RevisionControlKind::Darcs);
+        assert_eq!(detected.root, vendored);
+
+        let outer = detect_revision_control(&dir.path().join("vendor")).unwrap();
+        assert_eq!(outer.kind, RevisionControlKind::Git);
+
+        fs::create_dir(vendored.join(".git")).unwrap();
+        let same_dir = detect_revision_control(&cwd).unwrap();
+        assert_eq!(same_dir.kind, RevisionControlKind::Git);
+        assert_eq!(same_dir.root, vendored);
+    }
+
+    #[test]
+    fn program_override_falls_back_to_default() {
+        assert_eq!(
+            program_or_default(Some(OsString::from("/opt/git/bin/git")), "git"),
+            OsString::from("/opt/git/bin/git")
+        );
+        assert_eq!(
+            program_or_default(Some(OsString::new()), "git"),
+            OsString::from("git")
+        );
+        assert_eq!(program_or_default(None, "darcs"), OsString::from("darcs"));
+    }
+
     #[test]
     fn resolve_trust_root_for_darcs_repo() {
         let dir = tempdir().unwrap();
@@ -237,4 +819,79 @@ mod tests {
 
         assert_eq!(resolved, Some(dir.path().to_path_buf()));
     }
+
+    #[test]
+    fn detection_cache_drops_entries_whose_marker_disappears() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("_darcs")).unwrap();
+
+        let mut cache = DetectionCache::default();
+        let first = cache.detect(&root).unwrap();
+        assert_eq!(first.kind, RevisionControlKind::Darcs);
+        assert!(cache.entries.contains_key(&root));
+
+        fs::remove_dir(root.join("_darcs")).unwrap();
+        assert!(cache.detect(&root).is_none());
+        assert!(!cache.entries.contains_key(&root));
+
+        fs::create_dir(root.join("_darcs")).unwrap();
+        cache.detect(&root).unwrap();
+        cache.clear();
+        assert!(!cache.entries.contains_key(&root));
+    }
+
+    #[test]
+    fn detection_cache_recomputes_capabilities_on_hit() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+
+        let mut cache = DetectionCache::default();
+        assert!(!cache.detect(&root).unwrap().capabilities.supports_lfs);
+
+        fs::write(
+            root.join(".gitattributes"),
+            "*.bin filter=lfs diff=lfs merge=lfs -text\n",
+        )
+        .unwrap();
+        assert!(cache.detect(&root).unwrap().capabilities.supports_lfs);
+    }
+
+    #[test]
+    fn trust_root_resolves_git_worktrees_to_main_repository() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = root.join("repo");
+        let worktree = root.join("worktree");
+        fs::create_dir(&repo).unwrap();
+        let run_git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(&repo)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        run_git(&["init", "--initial-branch", "main"]);
+        run_git(&[
+            "-c",
+            "user.name=Tester",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--allow-empty",
+            "-m",
+            "initial",
+        ]);
+        run_git(&["worktree", "add", "--detach", worktree.to_str().unwrap()]);
+
+        let detected = detect_revision_control(&worktree).unwrap();
+        assert_eq!(detected.root, worktree);
+        assert_eq!(detected.trust_root(&worktree), Some(repo.clone()));
+        assert_eq!(
+            resolve_revision_control_project_for_trust(&worktree, None),
+            Some(repo)
+        );
+    }
 }
@@ -355,7 +355,7 @@ async fn rollout_writer(
     if let Some(session_meta) = meta.take() {
         let (revision_control_summary, git_info) = if let Some(backend) = revision_control.as_ref()
         {
-            let summary = collect_revision_control_summary(backend, &cwd).await;
+            let summary = collect_revision_control_summary(backend, &cwd, false).await;
             let git = summary.as_ref().and_then(|info| info.git.clone());
             (summary, git)
         } else {
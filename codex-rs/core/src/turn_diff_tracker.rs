@@ -10,7 +10,9 @@ use anyhow::anyhow;
 use sha1::digest::Output;
 use uuid::Uuid;
 
+use crate::git_info::git_command_env;
 use crate::protocol::FileChange;
+use crate::revision_control::git::git_program;
 
 const ZERO_OID: &str = "0000000000000000000000000000000000000000";
 const DEV_NULL: &str = "/dev/null";
@@ -204,7 +206,8 @@ impl TurnDiffTracker {
         let root = self.find_git_root_cached(path)?;
         // Compute a path relative to the repo root for better portability across platforms.
         let rel = path.strip_prefix(&root).unwrap_or(path);
-        let output = Command::new("git")
+        let output = Command::new(git_program())
+            .envs(git_command_env())
             .arg("-C")
             .arg(&root)
             .arg("hash-object")
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 use std::process::ExitStatus;
 use std::string::FromUtf8Error;
 
+use codex_core::git_info::PendingOperation;
 use codex_core::revision_control::RevisionControlKind;
 use thiserror::Error;
 use walkdir::Error as WalkdirError;
@@ -27,12 +28,51 @@ pub enum GitToolingError {
     NonRelativePath { path: PathBuf },
     #[error("path {path:?} escapes the repository root")]
     PathEscapesRepository { path: PathBuf },
+    #[error("path {path:?} is outside the repository")]
+    PathOutsideRepository { path: PathBuf },
     #[error("failed to process path inside worktree")]
     PathPrefix(#[from] std::path::StripPrefixError),
     #[error(transparent)]
     Walkdir(#[from] WalkdirError),
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    #[error("patch does not apply to {}", rejected.join(", "))]
+    PatchRejected { rejected: Vec<String> },
+    #[error("{id:?} does not name a commit in this repository")]
+    UnknownCommit { id: String },
+    #[error("{id:?} is ambiguous; it matches commits {}", candidates.join(", "))]
+    AmbiguousCommit { id: String, candidates: Vec<String> },
+    #[error(
+        "cannot snapshot {path:?} while a {operation} is in progress; finish or abort it first"
+    )]
+    OperationInProgress {
+        path: PathBuf,
+        operation: PendingOperation,
+    },
+    #[error("{path:?} is a bare repository without a working tree to snapshot")]
+    BareRepository { path: PathBuf },
     #[error("{kind:?} repositories are not supported for snapshot operations")]
     UnsupportedRevisionControl { kind: RevisionControlKind },
+    #[error(
+        "snapshot {short_id} was captured from a {snapshot_kind:?} repository and cannot be restored into a {backend_kind:?} workspace; restore it from the checkout it was taken in"
+    )]
+    MismatchedSnapshot {
+        short_id: String,
+        snapshot_kind: RevisionControlKind,
+        backend_kind: RevisionControlKind,
+    },
+}
+
+/// Failure of a [`crate::RepoSnapshotManager::with_snapshot`] transaction.
+#[derive(Debug, Error)]
+pub enum SnapshotError<E> {
+    /// Taking the snapshot failed, so the operation never ran.
+    #[error(transparent)]
+    Snapshot(#[from] GitToolingError),
+    /// The operation failed and the working tree was restored to the snapshot.
+    #[error("operation failed and was rolled back: {0}")]
+    RolledBack(#[source] E),
+    /// The operation failed and restoring the snapshot failed as well.
+    #[error("operation failed ({error}) and rolling back failed: {restore}")]
+    RollbackFailed { error: E, restore: GitToolingError },
 }
@@ -1,3 +1,6 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::ffi::OsString;
 use std::path::Path;
 use std::path::PathBuf;
@@ -8,21 +11,37 @@ use crate::GhostCommit;
 use crate::GitToolingError;
 use crate::operations::apply_repo_prefix_to_force_include;
 use crate::operations::ensure_git_repository;
+use crate::operations::ignores_case;
 use crate::operations::normalize_relative_path;
+use crate::operations::path_within_repo;
 use crate::operations::repo_subdir;
 use crate::operations::resolve_head;
 use crate::operations::resolve_repository_root;
 use crate::operations::run_git_for_status;
 use crate::operations::run_git_for_stdout;
+use crate::operations::run_git_for_stdout_with_input;
 
 /// Default commit message used for ghost commits when none is provided.
 const DEFAULT_COMMIT_MESSAGE: &str = "codex snapshot";
+/// Identity recorded as author and committer of ghost commits unless
+/// [`CreateGhostCommitOptions::author`] overrides it.
+const DEFAULT_AUTHOR_NAME: &str = "Codex Snapshot";
+const DEFAULT_AUTHOR_EMAIL: &str = "snapshot@codex.local";
+/// Message of the commit recording the index when [`CreateGhostCommitOptions::capture_index`]
+/// is set.
+const INDEX_COMMIT_MESSAGE: &str = "codex snapshot index";
+/// Namespace for refs that keep ghost commits reachable. Refs outside `refs/heads` and
+/// `refs/tags` protect commits from `git gc` without showing up in a plain `git log`.
+const SNAPSHOT_REF_PREFIX: &str = "refs/codex/snapshots/";
 
 /// Options to control ghost commit creation.
 pub struct CreateGhostCommitOptions<'a> {
     pub repo_path: &'a Path,
     pub message: Option<&'a str>,
+    pub author_name: Option<&'a str>,
+    pub author_email: Option<&'a str>,
     pub force_include: Vec<PathBuf>,
+    pub capture_index: bool,
 }
 
 impl<'a> CreateGhostCommitOptions<'a> {
@@ -31,7 +50,10 @@ impl<'a> CreateGhostCommitOptions<'a> {
         Self {
             repo_path,
             message: None,
+            author_name: None,
+            author_email: None,
             force_include: Vec::new(),
+            capture_index: false,
         }
     }
 
@@ -41,6 +63,14 @@ impl<'a> CreateGhostCommitOptions<'a> {
         self
     }
 
+    /// Records the ghost commit under this author and committer instead of
+    /// `Codex Snapshot <snapshot@codex.local>`.
+    pub fn author(mut self, name: &'a str, email: &'a str) -> Self {
+        self.author_name = Some(name);
+        self.author_email = Some(email);
+        self
+    }
+
     /// Supplies the entire force-include path list at once.
     pub fn force_include<I>(mut self, paths: I) -> Self
     where
@@ -50,6 +80,13 @@ impl<'a> CreateGhostCommitOptions<'a> {
         self
     }
 
+    /// Also records the index (staged state) so a restore can bring back the split
+    /// between staged and unstaged changes.
+    pub fn capture_index(mut self, capture_index: bool) -> Self {
+        self.capture_index = capture_index;
+        self
+    }
+
     /// Adds a single path to the force-include list.
     pub fn push_force_include<P>(mut self, path: P) -> Self
     where
@@ -61,6 +98,14 @@ impl<'a> CreateGhostCommitOptions<'a> {
 }
 
 /// Create a ghost commit capturing the current state of the repository's working tree.
+///
+/// Files excluded by `.gitignore`, `.git/info/exclude`, or `core.excludesFile` are
+/// skipped unless listed in [`CreateGhostCommitOptions::force_include`].
+///
+/// With [`CreateGhostCommitOptions::capture_index`], the index is committed separately and
+/// added as the ghost commit's second parent, the way `git stash` records it, so it stays
+/// reachable for as long as the ghost commit does. Repositories without a `HEAD` skip
+/// this, since the index commit could not be told apart from a `HEAD` parent.
 pub(crate) fn create_ghost_commit(
     options: &CreateGhostCommitOptions<'_>,
 ) -> Result<GhostCommit, GitToolingError> {
@@ -108,12 +153,23 @@ pub(crate) fn create_ghost_commit(
         Some(base_env.as_slice()),
     )?;
 
+    let identity = commit_identity(
+        options.author_name.unwrap_or(DEFAULT_AUTHOR_NAME),
+        options.author_email.unwrap_or(DEFAULT_AUTHOR_EMAIL),
+    );
+    let index_commit = match parent.as_deref() {
+        Some(parent) if options.capture_index => {
+            Some(commit_index(repo_root.as_path(), parent, &identity)?)
+        }
+        _ => None,
+    };
+
     let mut commit_env = base_env;
-    commit_env.extend(default_commit_identity());
+    commit_env.extend(identity);
     let message = options.message.unwrap_or(DEFAULT_COMMIT_MESSAGE);
     let commit_args = {
         let mut result = vec![OsString::from("commit-tree"), OsString::from(&tree_id)];
-        if let Some(parent) = parent.as_deref() {
+        for parent in parent.iter().chain(index_commit.iter()) {
             result.extend([OsString::from("-p"), OsString::from(parent)]);
         }
         result.extend([OsString::from("-m"), OsString::from(message)]);
@@ -127,7 +183,254 @@ pub(crate) fn create_ghost_commit(
         Some(commit_env.as_slice()),
     )?;
 
-    Ok(GhostCommit::new(commit_id, parent))
+    Ok(GhostCommit::new(commit_id, parent).with_index_commit(index_commit))
+}
+
+/// Commit the repository's real index on top of `parent` and return the commit id.
+fn commit_index(
+    repo_root: &Path,
+    parent: &str,
+    identity: &[(OsString, OsString)],
+) -> Result<String, GitToolingError> {
+    let index_tree = run_git_for_stdout(repo_root, vec![OsString::from("write-tree")], None)?;
+    run_git_for_stdout(
+        repo_root,
+        vec![
+            OsString::from("commit-tree"),
+            OsString::from(index_tree),
+            OsString::from("-p"),
+            OsString::from(parent),
+            OsString::from("-m"),
+            OsString::from(INDEX_COMMIT_MESSAGE),
+        ],
+        Some(identity),
+    )
+}
+
+/// Point `refs/codex/snapshots/<id>` at the ghost commit so it survives `git gc`.
+pub(crate) fn pin_ghost_commit(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<(), GitToolingError> {
+    run_git_for_status(
+        repo_path,
+        vec![
+            OsString::from("update-ref"),
+            OsString::from(format!("{SNAPSHOT_REF_PREFIX}{}", commit.id())),
+            OsString::from(commit.id()),
+        ],
+        None,
+    )
+}
+
+/// List pinned ghost commits, newest first.
+pub(crate) fn list_ghost_commits(repo_path: &Path) -> Result<Vec<GhostCommit>, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+
+    let output = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("for-each-ref"),
+            OsString::from("--sort=-committerdate"),
+            OsString::from("--format=%(objectname) %(parent)"),
+            OsString::from(SNAPSHOT_REF_PREFIX),
+        ],
+        None,
+    )?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let id = fields.next()?;
+            // A second parent is the index commit recorded by `capture_index`.
+            let parent = fields.next().map(str::to_string);
+            let index_commit = fields.next().map(str::to_string);
+            Some(GhostCommit::new(id.to_string(), parent).with_index_commit(index_commit))
+        })
+        .collect())
+}
+
+/// Delete every snapshot ref, leaving the ghost commits eligible for garbage collection.
+/// Returns the number of refs removed.
+pub(crate) fn clear_ghost_commit_refs(repo_path: &Path) -> Result<usize, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+
+    let refs = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("for-each-ref"),
+            OsString::from("--format=%(refname)"),
+            OsString::from(SNAPSHOT_REF_PREFIX),
+        ],
+        None,
+    )?;
+
+    let mut removed = 0;
+    for name in refs.lines().filter(|line| !line.is_empty()) {
+        run_git_for_status(
+            repo_path,
+            vec![
+                OsString::from("update-ref"),
+                OsString::from("-d"),
+                OsString::from(name),
+            ],
+            None,
+        )?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Disk space held by pinned snapshots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// Number of pinned snapshots.
+    pub snapshot_count: usize,
+    /// Bytes of object storage only the snapshots keep alive, i.e. what clearing every
+    /// snapshot ref would let `git gc` reclaim.
+    pub total_bytes: u64,
+}
+
+/// Measure the pinned snapshots and the objects reachable only through them.
+///
+/// Objects also reachable from branches, tags, remote-tracking branches, or `HEAD` are
+/// not counted, so unchanged files shared with real commits cost nothing.
+pub(crate) fn snapshot_storage_usage(repo_path: &Path) -> Result<StorageUsage, GitToolingError> {
+    let snapshots = list_ghost_commits(repo_path)?;
+    if snapshots.is_empty() {
+        return Ok(StorageUsage::default());
+    }
+
+    let mut rev_list_args = vec![OsString::from("rev-list"), OsString::from("--objects")];
+    rev_list_args.extend(snapshots.iter().map(|commit| OsString::from(commit.id())));
+    rev_list_args.extend([
+        OsString::from("--not"),
+        OsString::from("--branches"),
+        OsString::from("--tags"),
+        OsString::from("--remotes"),
+    ]);
+    if let Some(head) = resolve_head(repo_path)? {
+        rev_list_args.push(OsString::from(head));
+    }
+    let objects = run_git_for_stdout(repo_path, rev_list_args, None)?;
+
+    let object_ids: String = objects
+        .lines()
+        .filter_map(|line| line.split(' ').next())
+        .filter(|id| !id.is_empty())
+        .map(|id| format!("{id}\n"))
+        .collect();
+    let sizes = run_git_for_stdout_with_input(
+        repo_path,
+        vec![
+            OsString::from("cat-file"),
+            OsString::from("--batch-check=%(objectsize:disk)"),
+        ],
+        object_ids.as_bytes(),
+    )?;
+
+    Ok(StorageUsage {
+        snapshot_count: snapshots.len(),
+        total_bytes: sizes
+            .lines()
+            .filter_map(|size| size.trim().parse::<u64>().ok())
+            .sum(),
+    })
+}
+
+/// Revert tracked changes and remove untracked (non-ignored) files under `repo_path`,
+/// returning the affected paths relative to `repo_path`, sorted.
+///
+/// With `dry_run` set, the paths are reported from `git status --porcelain=v2` and nothing
+/// is modified.
+pub(crate) fn discard_changes(
+    repo_path: &Path,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+
+    let repo_root = resolve_repository_root(repo_path)?;
+    let repo_prefix = repo_subdir(repo_root.as_path(), repo_path);
+
+    let status = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("status"),
+            OsString::from("--porcelain=v2"),
+            OsString::from("-z"),
+            OsString::from("--untracked-files=all"),
+            OsString::from("--"),
+            OsString::from("."),
+        ],
+        None,
+    )?;
+
+    // Porcelain paths are relative to the repository root. The path is the last
+    // space-separated field of each entry, whose field count depends on its type.
+    let mut paths = Vec::new();
+    let mut entries = status.split('\0').filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        let fields = match entry.chars().next() {
+            Some('1') => 9,
+            Some('2') => 10,
+            Some('u') => 11,
+            Some('?') => 2,
+            _ => continue,
+        };
+        let Some(path) = entry.splitn(fields, ' ').nth(fields - 1) else {
+            continue;
+        };
+        paths.push(PathBuf::from(path));
+        // Renames and copies are followed by their source path.
+        if fields == 10
+            && let Some(source) = entries.next()
+        {
+            paths.push(PathBuf::from(source));
+        }
+    }
+    let mut paths: Vec<PathBuf> = paths
+        .into_iter()
+        .map(|path| match repo_prefix.as_deref() {
+            Some(prefix) => path
+                .strip_prefix(prefix)
+                .map(Path::to_path_buf)
+                .unwrap_or(path),
+            None => path,
+        })
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    if dry_run || paths.is_empty() {
+        return Ok(paths);
+    }
+
+    run_git_for_status(
+        repo_path,
+        vec![
+            OsString::from("restore"),
+            OsString::from("--source=HEAD"),
+            OsString::from("--staged"),
+            OsString::from("--worktree"),
+            OsString::from("--"),
+            OsString::from("."),
+        ],
+        None,
+    )?;
+    run_git_for_status(
+        repo_path,
+        vec![
+            OsString::from("clean"),
+            OsString::from("-f"),
+            OsString::from("-d"),
+            OsString::from("--"),
+            OsString::from("."),
+        ],
+        None,
+    )?;
+
+    Ok(paths)
 }
 
 /// Restore the working tree to match the provided ghost commit.
@@ -138,9 +441,143 @@ pub(crate) fn restore_ghost_commit(
     restore_to_commit(repo_path, commit.id())
 }
 
+/// Make the ghost commit available in `target_repo`, fetching it from `source_repo`
+/// (the repository it was captured in) when the target does not already have it.
+///
+/// A failed fetch is not an error here: restoring the commit afterwards reports it as
+/// [`GitToolingError::UnknownCommit`].
+pub(crate) fn import_ghost_commit(
+    source_repo: &Path,
+    target_repo: &Path,
+    commit: &GhostCommit,
+) -> Result<(), GitToolingError> {
+    ensure_git_repository(target_repo)?;
+    let present = run_git_for_status(
+        target_repo,
+        vec![
+            OsString::from("cat-file"),
+            OsString::from("-e"),
+            OsString::from(format!("{}^{{commit}}", commit.id())),
+        ],
+        None,
+    );
+    if present.is_ok() {
+        return Ok(());
+    }
+
+    let source_root = resolve_repository_root(source_repo)?;
+    match run_git_for_status(
+        target_repo,
+        vec![
+            OsString::from("fetch"),
+            OsString::from("--no-tags"),
+            OsString::from("--quiet"),
+            source_root.into_os_string(),
+            OsString::from(commit.id()),
+        ],
+        None,
+    ) {
+        Ok(()) | Err(GitToolingError::GitCommand { .. }) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Restore the working tree like [`restore_ghost_commit`], then put back the index recorded
+/// with [`CreateGhostCommitOptions::capture_index`] so staged and unstaged changes are
+/// split as they were. Snapshots without a recorded index are restored as usual.
+pub(crate) fn restore_ghost_commit_with_index(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<(), GitToolingError> {
+    restore_ghost_commit(repo_path, commit)?;
+    let Some(index_commit) = commit.index_commit() else {
+        return Ok(());
+    };
+
+    let repo_root = resolve_repository_root(repo_path)?;
+    let repo_prefix = repo_subdir(repo_root.as_path(), repo_path);
+    let mut restore_args = vec![
+        OsString::from("restore"),
+        OsString::from("--source"),
+        OsString::from(index_commit),
+        OsString::from("--staged"),
+        OsString::from("--"),
+    ];
+    match repo_prefix {
+        Some(prefix) => restore_args.push(prefix.into_os_string()),
+        None => restore_args.push(OsString::from(".")),
+    }
+    run_git_for_status(repo_root.as_path(), restore_args, None)
+}
+
+/// Files a completed restore changed, grouped by the effect it had on the working tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+    /// Files whose contents were overwritten.
+    pub modified: Vec<PathBuf>,
+    /// Files the restore recreated.
+    pub created: Vec<PathBuf>,
+    /// Files the restore removed.
+    pub deleted: Vec<PathBuf>,
+}
+
+impl RestoreReport {
+    /// Number of files the restore touched.
+    pub fn len(&self) -> usize {
+        self.modified.len() + self.created.len() + self.deleted.len()
+    }
+
+    /// Whether the restore left every file as it was.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Restore the working tree to match the ghost commit and report what changed.
+///
+/// The working tree is captured before and after the restore and the two states are
+/// diffed, so the report reflects what actually happened rather than a prediction.
+/// Paths are relative to `repo_path`.
+pub(crate) fn restore_ghost_commit_reporting(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<RestoreReport, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+    let before = create_ghost_commit(&CreateGhostCommitOptions::new(repo_path))?;
+    restore_ghost_commit(repo_path, commit)?;
+    let after = create_ghost_commit(&CreateGhostCommitOptions::new(repo_path))?;
+
+    let changes = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("diff"),
+            OsString::from("--name-status"),
+            OsString::from("--no-renames"),
+            OsString::from("--relative"),
+            OsString::from("-z"),
+            OsString::from(before.id()),
+            OsString::from(after.id()),
+        ],
+        None,
+    )?;
+
+    let mut report = RestoreReport::default();
+    let mut fields = changes.split('\0').filter(|field| !field.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        let path = PathBuf::from(path);
+        match status {
+            "A" => report.created.push(path),
+            "D" => report.deleted.push(path),
+            _ => report.modified.push(path),
+        }
+    }
+    Ok(report)
+}
+
 /// Restore the working tree to match the given commit ID.
 pub(crate) fn restore_to_commit(repo_path: &Path, commit_id: &str) -> Result<(), GitToolingError> {
     ensure_git_repository(repo_path)?;
+    let commit_id = resolve_commit(repo_path, commit_id)?;
 
     let repo_root = resolve_repository_root(repo_path)?;
     let repo_prefix = repo_subdir(repo_root.as_path(), repo_path);
@@ -163,31 +600,499 @@ pub(crate) fn restore_to_commit(repo_path: &Path, commit_id: &str) -> Result<(),
     Ok(())
 }
 
-/// Returns the default author and committer identity for ghost commits.
-fn default_commit_identity() -> Vec<(OsString, OsString)> {
+/// Restore only `paths` from the ghost commit, leaving the rest of the working tree alone.
+///
+/// Paths are relative to `repo_path` (or absolute) and must stay inside the repository.
+pub(crate) fn restore_paths(
+    repo_path: &Path,
+    commit: &GhostCommit,
+    paths: &[PathBuf],
+) -> Result<(), GitToolingError> {
+    ensure_git_repository(repo_path)?;
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let repo_root = resolve_repository_root(repo_path)?;
+    let mut checkout_args = vec![
+        OsString::from("checkout"),
+        OsString::from(commit.id()),
+        OsString::from("--"),
+    ];
+    for path in paths {
+        let relative = path_within_repo(repo_root.as_path(), repo_path, path)?;
+        checkout_args.push(relative.into_os_string());
+    }
+
+    run_git_for_status(repo_root.as_path(), checkout_args, None)
+}
+
+/// Whether the ghost commit's parent no longer exists in the repository, e.g. because
+/// history was rewritten and the old commit was garbage collected. Snapshots taken
+/// without a `HEAD` have no parent and are never orphaned.
+pub(crate) fn ghost_commit_is_orphaned(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<bool, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+    let Some(parent) = commit.parent() else {
+        return Ok(false);
+    };
+
+    match run_git_for_status(
+        repo_path,
+        vec![
+            OsString::from("cat-file"),
+            OsString::from("-e"),
+            OsString::from(format!("{parent}^{{commit}}")),
+        ],
+        None,
+    ) {
+        Ok(()) => Ok(false),
+        Err(GitToolingError::GitCommand { .. }) => Ok(true),
+        Err(err) => Err(err),
+    }
+}
+
+/// Read back the full commit message recorded for the ghost commit.
+pub(crate) fn ghost_commit_message(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<String, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+    run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("log"),
+            OsString::from("-1"),
+            OsString::from("--format=%B"),
+            OsString::from(commit.id()),
+            OsString::from("--"),
+        ],
+        None,
+    )
+}
+
+/// Unified diff from ghost commit `from` to ghost commit `to`.
+pub(crate) fn diff_ghost_commits(
+    repo_path: &Path,
+    from: &GhostCommit,
+    to: &GhostCommit,
+) -> Result<String, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+    run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("diff"),
+            OsString::from("--no-color"),
+            OsString::from("--no-ext-diff"),
+            OsString::from(from.id()),
+            OsString::from(to.id()),
+            OsString::from("--"),
+        ],
+        None,
+    )
+}
+
+/// Resolve `commit_id` to the full id of a commit in the repository.
+///
+/// Only plain revisions are accepted: anything that could be read as an option, a
+/// revision range, a `rev:path` lookup, or a filesystem path is rejected up front so
+/// it cannot reach outside the repository. An abbreviated hash shared by several
+/// commits fails with [`GitToolingError::AmbiguousCommit`] listing them.
+fn resolve_commit(repo_path: &Path, commit_id: &str) -> Result<String, GitToolingError> {
+    let unknown = || GitToolingError::UnknownCommit {
+        id: commit_id.to_string(),
+    };
+    let looks_like_ref_outside_repo = commit_id.is_empty()
+        || commit_id.starts_with(['-', '/'])
+        || commit_id.contains("..")
+        || commit_id.contains([':', '\\'])
+        || commit_id.contains(char::is_whitespace);
+    if looks_like_ref_outside_repo {
+        return Err(unknown());
+    }
+
+    match run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("rev-parse"),
+            OsString::from("--verify"),
+            OsString::from("--quiet"),
+            OsString::from(format!("{commit_id}^{{commit}}")),
+        ],
+        None,
+    ) {
+        Ok(sha) => Ok(sha),
+        Err(GitToolingError::GitCommand { .. }) => {
+            let candidates = commits_with_prefix(repo_path, commit_id)?;
+            if candidates.len() > 1 {
+                Err(GitToolingError::AmbiguousCommit {
+                    id: commit_id.to_string(),
+                    candidates,
+                })
+            } else {
+                Err(unknown())
+            }
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Full ids of the commits whose hash starts with `prefix`. Empty when `prefix` is not
+/// an abbreviated hash git can disambiguate (e.g. shorter than four characters).
+fn commits_with_prefix(repo_path: &Path, prefix: &str) -> Result<Vec<String>, GitToolingError> {
+    if !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(Vec::new());
+    }
+    let objects = match run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("rev-parse"),
+            OsString::from(format!("--disambiguate={prefix}")),
+        ],
+        None,
+    ) {
+        Ok(objects) => objects,
+        Err(GitToolingError::GitCommand { .. }) => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    if objects.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let input: String = objects.lines().map(|id| format!("{id}\n")).collect();
+    let types = run_git_for_stdout_with_input(
+        repo_path,
+        vec![
+            OsString::from("cat-file"),
+            OsString::from("--batch-check=%(objecttype) %(objectname)"),
+        ],
+        input.as_bytes(),
+    )?;
+    Ok(types
+        .lines()
+        .filter_map(|line| line.strip_prefix("commit "))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Files a restore would touch, grouped by the effect it would have on the working tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestorePlan {
+    /// Existing files whose contents would be overwritten.
+    pub modified: Vec<PathBuf>,
+    /// Tracked files absent from the snapshot that would be removed.
+    pub deleted: Vec<PathBuf>,
+    /// Files missing from the working tree that would be recreated.
+    pub created: Vec<PathBuf>,
+    /// Modified or deleted files whose current contents are not committed, so the
+    /// restore would discard work that cannot be recovered from history.
+    pub conflicts: Vec<PathBuf>,
+    /// Paths in the snapshot or working tree that differ from another one only by case.
+    /// Only reported when `core.ignorecase` is set, since the restore would then write
+    /// them to the same file.
+    pub case_collisions: Vec<PathBuf>,
+}
+
+impl RestorePlan {
+    /// Every path the restore would overwrite, create, or delete, sorted.
+    pub fn touched_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .modified
+            .iter()
+            .chain(&self.deleted)
+            .chain(&self.created)
+            .cloned()
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+/// Describe what restoring `commit` would do without touching the working tree.
+///
+/// Paths are relative to `repo_path`, and only changes under it are reported.
+pub(crate) fn restore_plan(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<RestorePlan, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+
+    let changes = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("diff"),
+            OsString::from("--name-status"),
+            OsString::from("--no-renames"),
+            OsString::from("--relative"),
+            OsString::from("-z"),
+            OsString::from(commit.id()),
+        ],
+        None,
+    )?;
+
+    let mut plan = RestorePlan::default();
+    let mut fields = changes.split('\0').filter(|field| !field.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        let path = PathBuf::from(path);
+        match status {
+            // Present in the snapshot only. Untracked files still on disk are
+            // reported here too, and the restore would overwrite them.
+            "D" if std::fs::symlink_metadata(repo_path.join(&path)).is_ok() => {
+                plan.modified.push(path)
+            }
+            "D" => plan.created.push(path),
+            "A" => plan.deleted.push(path),
+            _ => plan.modified.push(path),
+        }
+    }
+
+    let uncommitted = uncommitted_paths(repo_path)?;
+    plan.conflicts = plan
+        .modified
+        .iter()
+        .chain(plan.deleted.iter())
+        .filter(|path| {
+            uncommitted
+                .as_ref()
+                .is_none_or(|uncommitted| uncommitted.contains(*path))
+        })
+        .cloned()
+        .collect();
+    plan.conflicts.sort();
+
+    if ignores_case(repo_path)? {
+        plan.case_collisions = case_collisions(repo_path, commit)?;
+    }
+
+    Ok(plan)
+}
+
+/// Paths under `repo_path`, in `commit` or known to the working tree, that share their
+/// lowercase spelling with a different path. Sorted.
+fn case_collisions(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<Vec<PathBuf>, GitToolingError> {
+    let snapshot = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("ls-tree"),
+            OsString::from("-r"),
+            OsString::from("-z"),
+            OsString::from("--name-only"),
+            OsString::from(commit.id()),
+        ],
+        None,
+    )?;
+    let worktree = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("ls-files"),
+            OsString::from("--cached"),
+            OsString::from("--others"),
+            OsString::from("--exclude-standard"),
+            OsString::from("-z"),
+        ],
+        None,
+    )?;
+
+    let paths: BTreeSet<&str> = snapshot
+        .split('\0')
+        .chain(worktree.split('\0'))
+        .filter(|path| !path.is_empty())
+        .collect();
+    let mut by_folded: HashMap<String, Vec<&str>> = HashMap::new();
+    for path in paths {
+        by_folded.entry(path.to_lowercase()).or_default().push(path);
+    }
+
+    let mut collisions: Vec<PathBuf> = by_folded
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .flatten()
+        .map(PathBuf::from)
+        .collect();
+    collisions.sort();
+    Ok(collisions)
+}
+
+/// Paths under `repo_path` whose working-tree contents differ from `HEAD`, including
+/// untracked files. Returns `None` when there is no `HEAD`, meaning nothing is committed.
+fn uncommitted_paths(repo_path: &Path) -> Result<Option<HashSet<PathBuf>>, GitToolingError> {
+    if resolve_head(repo_path)?.is_none() {
+        return Ok(None);
+    }
+
+    let tracked = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("diff"),
+            OsString::from("--name-only"),
+            OsString::from("--no-renames"),
+            OsString::from("--relative"),
+            OsString::from("-z"),
+            OsString::from("HEAD"),
+        ],
+        None,
+    )?;
+    let untracked = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("ls-files"),
+            OsString::from("--others"),
+            OsString::from("-z"),
+        ],
+        None,
+    )?;
+
+    Ok(Some(
+        tracked
+            .split('\0')
+            .chain(untracked.split('\0'))
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+    ))
+}
+
+/// How a file changed relative to a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeStatus {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A file whose working-tree state differs from a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    /// Path relative to the repository path the comparison was run from.
+    pub path: PathBuf,
+    pub status: ChangeStatus,
+}
+
+/// List files whose current state differs from the ghost commit, without computing
+/// their diffs.
+///
+/// The working tree is staged into a temporary index seeded from the snapshot, so
+/// untracked and force-included files are compared the same way they were captured.
+pub(crate) fn changed_files_vs_snapshot(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<Vec<ChangedFile>, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+
+    let repo_root = resolve_repository_root(repo_path)?;
+    let repo_prefix = repo_subdir(repo_root.as_path(), repo_path);
+    let index_tempdir = Builder::new().prefix("codex-git-index-").tempdir()?;
+    let index_path = index_tempdir.path().join("index");
+    let env = vec![(
+        OsString::from("GIT_INDEX_FILE"),
+        OsString::from(index_path.as_os_str()),
+    )];
+
+    run_git_for_status(
+        repo_root.as_path(),
+        vec![OsString::from("read-tree"), OsString::from(commit.id())],
+        Some(env.as_slice()),
+    )?;
+    let mut add_args = vec![OsString::from("add"), OsString::from("--all")];
+    if let Some(prefix) = repo_prefix.as_deref() {
+        add_args.extend([OsString::from("--"), prefix.as_os_str().to_os_string()]);
+    }
+    run_git_for_status(repo_root.as_path(), add_args, Some(env.as_slice()))?;
+
+    let changes = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("diff"),
+            OsString::from("--cached"),
+            OsString::from("--name-status"),
+            OsString::from("--no-renames"),
+            OsString::from("--relative"),
+            OsString::from("-z"),
+            OsString::from(commit.id()),
+        ],
+        Some(env.as_slice()),
+    )?;
+
+    let mut changed = Vec::new();
+    let mut fields = changes.split('\0').filter(|field| !field.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        let status = match status {
+            "A" => ChangeStatus::Added,
+            "D" => ChangeStatus::Deleted,
+            _ => ChangeStatus::Modified,
+        };
+        changed.push(ChangedFile {
+            path: PathBuf::from(path),
+            status,
+        });
+    }
+    Ok(changed)
+}
+
+/// Recreate files captured in the ghost commit that are missing from the working tree.
+///
+/// Files that still exist are left untouched, even if their contents differ from the
+/// snapshot. Returned paths are relative to `repo_path`.
+pub(crate) fn restore_missing_files(
+    repo_path: &Path,
+    commit: &GhostCommit,
+) -> Result<Vec<PathBuf>, GitToolingError> {
+    ensure_git_repository(repo_path)?;
+
+    // `ls-tree` limits the listing to the current directory and reports paths relative
+    // to it, which keeps subdirectory workspaces scoped like `restore_to_commit`.
+    let listing = run_git_for_stdout(
+        repo_path,
+        vec![
+            OsString::from("ls-tree"),
+            OsString::from("-r"),
+            OsString::from("-z"),
+            OsString::from("--name-only"),
+            OsString::from(commit.id()),
+        ],
+        None,
+    )?;
+
+    let missing: Vec<PathBuf> = listing
+        .split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| std::fs::symlink_metadata(repo_path.join(path)).is_err())
+        .collect();
+    if missing.is_empty() {
+        return Ok(missing);
+    }
+
+    let mut checkout_args = vec![
+        OsString::from("checkout"),
+        OsString::from(commit.id()),
+        OsString::from("--"),
+    ];
+    checkout_args.extend(missing.iter().map(|path| path.as_os_str().to_os_string()));
+    run_git_for_status(repo_path, checkout_args, None)?;
+
+    Ok(missing)
+}
+
+/// Returns the environment that records `name <email>` as both author and committer.
+fn commit_identity(name: &str, email: &str) -> Vec<(OsString, OsString)> {
     vec![
-        (
-            OsString::from("GIT_AUTHOR_NAME"),
-            OsString::from("Codex Snapshot"),
-        ),
-        (
-            OsString::from("GIT_AUTHOR_EMAIL"),
-            OsString::from("snapshot@codex.local"),
-        ),
-        (
-            OsString::from("GIT_COMMITTER_NAME"),
-            OsString::from("Codex Snapshot"),
-        ),
-        (
-            OsString::from("GIT_COMMITTER_EMAIL"),
-            OsString::from("snapshot@codex.local"),
-        ),
+        (OsString::from("GIT_AUTHOR_NAME"), OsString::from(name)),
+        (OsString::from("GIT_AUTHOR_EMAIL"), OsString::from(email)),
+        (OsString::from("GIT_COMMITTER_NAME"), OsString::from(name)),
+        (OsString::from("GIT_COMMITTER_EMAIL"), OsString::from(email)),
     ]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::operations::background_maintenance_configured;
     use crate::operations::run_git_for_stdout;
     use assert_matches::assert_matches;
     use pretty_assertions::assert_eq;
@@ -343,24 +1248,933 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(unix)]
     #[test]
-    /// Rejects force-included paths that escape the repository.
-    fn create_ghost_commit_rejects_force_include_parent_path() {
-        let temp = tempfile::tempdir().expect("tempdir");
+    /// Snapshots are built from plumbing commands, so commit hooks never run.
+    fn create_ghost_commit_does_not_run_commit_hooks() -> Result<(), GitToolingError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir()?;
         let repo = temp.path();
         init_test_repo(repo);
-        let options = CreateGhostCommitOptions::new(repo)
-            .force_include(vec![PathBuf::from("../outside.txt")]);
-        let err = create_ghost_commit(&options).unwrap_err();
-        assert_matches!(err, GitToolingError::PathEscapesRepository { .. });
+        std::fs::write(repo.join("tracked.txt"), "contents\n")?;
+        let marker = temp.path().join("hook-ran");
+        let hooks = repo.join(".git/hooks");
+        std::fs::create_dir_all(&hooks)?;
+        for hook in ["pre-commit", "commit-msg", "post-commit"] {
+            let path = hooks.join(hook);
+            std::fs::write(
+                &path,
+                format!("#!/bin/sh\necho {hook} >> '{}'\n", marker.display()),
+            )?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        assert!(
+            !marker.exists(),
+            "a commit hook ran during snapshot creation"
+        );
+        let contents = run_git_stdout(repo, &["show", &format!("{}:tracked.txt", ghost.id())]);
+        assert_eq!(contents, "contents");
+        Ok(())
     }
 
     #[test]
-    /// Restoring a ghost commit from a non-git directory fails.
-    fn restore_requires_git_repository() {
-        let temp = tempfile::tempdir().expect("tempdir");
-        let err = restore_to_commit(temp.path(), "deadbeef").unwrap_err();
-        assert_matches!(err, GitToolingError::NotAGitRepository { .. });
+    /// Ghost commits default to the Codex Snapshot identity and accept an override.
+    fn create_ghost_commit_uses_configured_author() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("tracked.txt"), "contents\n")?;
+        run_git_in(repo, &["add", "tracked.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+        let identity =
+            |id: &str| run_git_stdout(repo, &["log", "-1", "--format=%an <%ae>|%cn <%ce>", id]);
+
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+        assert_eq!(
+            identity(ghost.id()),
+            "Codex Snapshot <snapshot@codex.local>|Codex Snapshot <snapshot@codex.local>"
+        );
+
+        let ghost = create_ghost_commit(
+            &CreateGhostCommitOptions::new(repo)
+                .author("Review Bot", "bot@example.com")
+                .capture_index(true),
+        )?;
+        assert_eq!(
+            identity(ghost.id()),
+            "Review Bot <bot@example.com>|Review Bot <bot@example.com>"
+        );
+        let index_commit = ghost.index_commit().expect("index captured");
+        assert_eq!(
+            identity(index_commit),
+            "Review Bot <bot@example.com>|Review Bot <bot@example.com>"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// A snapshot whose parent commit has been removed is reported as orphaned.
+    fn ghost_commit_is_orphaned_detects_missing_parent() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("tracked.txt"), "contents\n")?;
+        run_git_in(repo, &["add", "tracked.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+        assert!(!ghost_commit_is_orphaned(repo, &ghost)?);
+
+        let parent = ghost.parent().expect("snapshot has a parent").to_string();
+        let (dir, file) = parent.split_at(2);
+        std::fs::remove_file(repo.join(".git/objects").join(dir).join(file))?;
+        assert!(ghost_commit_is_orphaned(repo, &ghost)?);
+
+        let unparented = GhostCommit::new(ghost.id().to_string(), None);
+        assert!(!ghost_commit_is_orphaned(repo, &unparented)?);
+
+        Ok(())
+    }
+
+    #[test]
+    /// A maintenance schedule or explicit `maintenance.auto` is reported as background gc.
+    fn detects_background_maintenance_config() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+
+        assert!(!background_maintenance_configured(repo)?);
+
+        run_git_in(repo, &["config", "maintenance.strategy", "none"]);
+        assert!(!background_maintenance_configured(repo)?);
+
+        run_git_in(repo, &["config", "maintenance.strategy", "incremental"]);
+        assert!(background_maintenance_configured(repo)?);
+
+        run_git_in(repo, &["config", "--unset", "maintenance.strategy"]);
+        run_git_in(repo, &["config", "maintenance.auto", "true"]);
+        assert!(background_maintenance_configured(repo)?);
+
+        Ok(())
+    }
+
+    #[test]
+    /// Storage usage counts pinned snapshots and the objects only they reference.
+    fn storage_usage_sums_snapshot_only_objects() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("tracked.txt"), "committed\n")?;
+        run_git_in(repo, &["add", "tracked.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        assert_eq!(snapshot_storage_usage(repo)?, StorageUsage::default());
+
+        // Objects shared with HEAD are free, so the first snapshot only pays for its
+        // commit and tree.
+        let first = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+        pin_ghost_commit(repo, &first)?;
+        let unchanged = snapshot_storage_usage(repo)?;
+        assert_eq!(unchanged.snapshot_count, 1);
+
+        std::fs::write(repo.join("notes.txt"), "snapshot only\n".repeat(100))?;
+        let second = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+        pin_ghost_commit(repo, &second)?;
+        let usage = snapshot_storage_usage(repo)?;
+        assert_eq!(usage.snapshot_count, 2);
+        assert!(usage.total_bytes > unchanged.total_bytes);
+
+        clear_ghost_commit_refs(repo)?;
+        assert_eq!(snapshot_storage_usage(repo)?, StorageUsage::default());
+
+        Ok(())
+    }
+
+    #[test]
+    /// Capturing the index lets a restore keep staged and unstaged changes apart.
+    fn restore_with_index_preserves_staging() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("staged.txt"), "initial\n")?;
+        std::fs::write(repo.join("unstaged.txt"), "initial\n")?;
+        run_git_in(repo, &["add", "."]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        std::fs::write(repo.join("staged.txt"), "staged change\n")?;
+        run_git_in(repo, &["add", "staged.txt"]);
+        std::fs::write(repo.join("unstaged.txt"), "unstaged change\n")?;
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo).capture_index(true))?;
+        assert!(ghost.index_commit().is_some());
+
+        std::fs::write(repo.join("staged.txt"), "mutated\n")?;
+        std::fs::write(repo.join("unstaged.txt"), "mutated\n")?;
+        run_git_in(repo, &["add", "."]);
+
+        restore_ghost_commit_with_index(repo, &ghost)?;
+
+        assert_eq!(
+            std::fs::read_to_string(repo.join("staged.txt"))?,
+            "staged change\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.join("unstaged.txt"))?,
+            "unstaged change\n"
+        );
+        assert_eq!(
+            run_git_stdout(repo, &["diff", "--cached", "--name-only"]),
+            "staged.txt"
+        );
+        assert_eq!(
+            run_git_stdout(repo, &["diff", "--name-only"]),
+            "unstaged.txt"
+        );
+
+        pin_ghost_commit(repo, &ghost)?;
+        assert_eq!(list_ghost_commits(repo)?, vec![ghost]);
+
+        Ok(())
+    }
+
+    #[test]
+    /// The restore report lists exactly the files the restore changed.
+    fn restore_reporting_lists_touched_files() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("tracked.txt"), "snapshot\n")?;
+        std::fs::write(repo.join("removed.txt"), "committed\n")?;
+        std::fs::write(repo.join("unchanged.txt"), "committed\n")?;
+        run_git_in(repo, &["add", "."]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        std::fs::remove_file(repo.join("removed.txt"))?;
+        std::fs::write(repo.join("new.txt"), "untracked\n")?;
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        std::fs::write(repo.join("tracked.txt"), "edited\n")?;
+        run_git_in(repo, &["checkout", "--", "removed.txt"]);
+        std::fs::remove_file(repo.join("new.txt"))?;
+
+        let report = restore_ghost_commit_reporting(repo, &ghost)?;
+
+        assert_eq!(
+            report,
+            RestoreReport {
+                modified: vec![PathBuf::from("tracked.txt")],
+                created: vec![PathBuf::from("new.txt")],
+                deleted: vec![PathBuf::from("removed.txt")],
+            }
+        );
+        assert_eq!(report.len(), 3);
+        assert_eq!(
+            std::fs::read_to_string(repo.join("tracked.txt"))?,
+            "snapshot\n"
+        );
+        assert!(!repo.join("removed.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    /// The recorded message, including its body, is read back verbatim.
+    fn ghost_commit_message_reads_back_message() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("tracked.txt"), "contents\n")?;
+
+        let message = "before edit\n\nturn 3: apply patch to tracked.txt";
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo).message(message))?;
+        assert_eq!(ghost_commit_message(repo, &ghost)?, message);
+
+        let default = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+        assert_eq!(
+            ghost_commit_message(repo, &default)?,
+            DEFAULT_COMMIT_MESSAGE
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// Paths excluded through `.git/info/exclude` are left out of the snapshot.
+    fn create_ghost_commit_honors_info_exclude() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+
+        std::fs::write(repo.join(".git/info/exclude"), "local-only.txt\n")?;
+        std::fs::write(repo.join("local-only.txt"), "private\n")?;
+        std::fs::write(repo.join("visible.txt"), "visible\n")?;
+
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        let files = run_git_stdout(repo, &["ls-tree", "-r", "--name-only", ghost.id()]);
+        assert_eq!(files, "visible.txt");
+
+        Ok(())
+    }
+
+    #[test]
+    /// Rejects force-included paths that escape the repository.
+    fn create_ghost_commit_rejects_force_include_parent_path() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let repo = temp.path();
+        init_test_repo(repo);
+        let options = CreateGhostCommitOptions::new(repo)
+            .force_include(vec![PathBuf::from("../outside.txt")]);
+        let err = create_ghost_commit(&options).unwrap_err();
+        assert_matches!(err, GitToolingError::PathEscapesRepository { .. });
+    }
+
+    #[test]
+    /// Only files deleted since the snapshot are recreated; edits are kept.
+    fn restore_missing_files_recreates_only_deleted_files() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+
+        std::fs::create_dir_all(repo.join("nested"))?;
+        std::fs::write(repo.join("edited.txt"), "original\n")?;
+        std::fs::write(repo.join("first.txt"), "first\n")?;
+        std::fs::write(repo.join("nested/second.txt"), "second\n")?;
+        run_git_in(repo, &["add", "."]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        std::fs::write(repo.join("edited.txt"), "edited after snapshot\n")?;
+        std::fs::remove_file(repo.join("first.txt"))?;
+        std::fs::remove_file(repo.join("nested/second.txt"))?;
+
+        let restored = restore_missing_files(repo, &ghost)?;
+
+        assert_eq!(
+            restored,
+            vec![
+                PathBuf::from("first.txt"),
+                PathBuf::from("nested/second.txt")
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(repo.join("first.txt"))?, "first\n");
+        assert_eq!(
+            std::fs::read_to_string(repo.join("nested/second.txt"))?,
+            "second\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.join("edited.txt"))?,
+            "edited after snapshot\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// The restore plan classifies every change without touching the working tree.
+    fn restore_plan_classifies_changes() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+
+        for name in ["conflict.txt", "recreate.txt", "committed.txt"] {
+            std::fs::write(repo.join(name), format!("{name} initial\n"))?;
+        }
+        run_git_in(repo, &["add", "."]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        std::fs::write(repo.join("conflict.txt"), "snapshot contents\n")?;
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        std::fs::write(repo.join("conflict.txt"), "unsaved edit\n")?;
+        std::fs::remove_file(repo.join("recreate.txt"))?;
+        std::fs::write(repo.join("committed.txt"), "committed later\n")?;
+        std::fs::write(repo.join("added.txt"), "added later\n")?;
+        std::fs::write(repo.join("untracked.txt"), "untouched\n")?;
+        run_git_in(repo, &["add", "committed.txt", "added.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "later",
+            ],
+        );
+
+        let plan = restore_plan(repo, &ghost)?;
+
+        assert_eq!(
+            plan,
+            RestorePlan {
+                modified: vec![
+                    PathBuf::from("committed.txt"),
+                    PathBuf::from("conflict.txt")
+                ],
+                deleted: vec![PathBuf::from("added.txt")],
+                created: vec![PathBuf::from("recreate.txt")],
+                conflicts: vec![PathBuf::from("conflict.txt")],
+                case_collisions: Vec::new(),
+            }
+        );
+        assert_eq!(
+            plan.touched_paths(),
+            vec![
+                PathBuf::from("added.txt"),
+                PathBuf::from("committed.txt"),
+                PathBuf::from("conflict.txt"),
+                PathBuf::from("recreate.txt"),
+            ]
+        );
+        assert!(!repo.join("recreate.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(repo.join("conflict.txt"))?,
+            "unsaved edit\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// With `core.ignorecase` set, the restore plan flags paths that differ only by case.
+    fn restore_plan_reports_case_collisions_when_ignoring_case() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        run_git_in(repo, &["config", "core.ignorecase", "false"]);
+
+        // Stage the colliding spellings through the index so the test does not depend on
+        // the filesystem being case-sensitive.
+        std::fs::write(repo.join("readme.md"), "lower\n")?;
+        std::fs::write(repo.join("other.txt"), "other\n")?;
+        run_git_in(repo, &["add", "readme.md", "other.txt"]);
+        let blob = run_git_stdout(repo, &["hash-object", "-w", "readme.md"]);
+        run_git_in(
+            repo,
+            &[
+                "update-index",
+                "--add",
+                "--cacheinfo",
+                &format!("100644,{blob},README.md"),
+            ],
+        );
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+        let ghost = GhostCommit::new(run_git_stdout(repo, &["rev-parse", "HEAD"]), None);
+
+        assert!(!ignores_case(repo)?);
+        assert_eq!(
+            restore_plan(repo, &ghost)?.case_collisions,
+            Vec::<PathBuf>::new()
+        );
+
+        run_git_in(repo, &["config", "core.ignorecase", "true"]);
+
+        assert!(ignores_case(repo)?);
+        assert_eq!(
+            restore_plan(repo, &ghost)?.case_collisions,
+            vec![PathBuf::from("README.md"), PathBuf::from("readme.md")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// Dry runs report discardable paths without touching them; real runs discard them.
+    fn discard_changes_dry_run_and_apply() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+
+        std::fs::write(repo.join(".gitignore"), "ignored.txt\n")?;
+        std::fs::write(repo.join("modified.txt"), "initial\n")?;
+        std::fs::write(repo.join("deleted.txt"), "initial\n")?;
+        run_git_in(repo, &["add", "."]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        std::fs::write(repo.join("modified.txt"), "changed\n")?;
+        std::fs::remove_file(repo.join("deleted.txt"))?;
+        std::fs::write(repo.join("staged.txt"), "staged\n")?;
+        run_git_in(repo, &["add", "staged.txt"]);
+        std::fs::create_dir(repo.join("dir"))?;
+        std::fs::write(repo.join("dir").join("untracked.txt"), "untracked\n")?;
+        std::fs::write(repo.join("ignored.txt"), "ignored\n")?;
+
+        let expected = vec![
+            PathBuf::from("deleted.txt"),
+            PathBuf::from("dir/untracked.txt"),
+            PathBuf::from("modified.txt"),
+            PathBuf::from("staged.txt"),
+        ];
+
+        assert_eq!(discard_changes(repo, true)?, expected);
+        assert_eq!(
+            std::fs::read_to_string(repo.join("modified.txt"))?,
+            "changed\n"
+        );
+        assert!(!repo.join("deleted.txt").exists());
+        assert!(repo.join("dir").join("untracked.txt").exists());
+
+        assert_eq!(discard_changes(repo, false)?, expected);
+        assert_eq!(
+            std::fs::read_to_string(repo.join("modified.txt"))?,
+            "initial\n"
+        );
+        assert!(repo.join("deleted.txt").exists());
+        assert!(!repo.join("staged.txt").exists());
+        assert!(!repo.join("dir").exists());
+        assert!(repo.join("ignored.txt").exists());
+        assert_eq!(run_git_stdout(repo, &["status", "--porcelain"]), "");
+
+        Ok(())
+    }
+
+    #[test]
+    /// Pinned ghost commits are listed newest first and can be unpinned.
+    fn snapshot_refs_round_trip() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+
+        std::fs::write(repo.join("tracked.txt"), "initial\n")?;
+        run_git_in(repo, &["add", "tracked.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        // Committer dates have one-second resolution, so two snapshots taken back to back
+        // tie. Commit with explicit dates to make the ordering unambiguous.
+        let head = run_git_stdout(repo, &["rev-parse", "HEAD"]);
+        let commit_at = |date: &str| {
+            let output = Command::new("git")
+                .current_dir(repo)
+                .env("GIT_AUTHOR_DATE", date)
+                .env("GIT_COMMITTER_DATE", date)
+                .args([
+                    "-c",
+                    "user.name=Tester",
+                    "-c",
+                    "user.email=test@example.com",
+                    "commit-tree",
+                    "HEAD^{tree}",
+                    "-p",
+                    "HEAD",
+                    "-m",
+                    date,
+                ])
+                .output()
+                .expect("git command");
+            assert!(output.status.success(), "git commit-tree failed");
+            let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            GhostCommit::new(id, Some(head.clone()))
+        };
+
+        let second = commit_at("2024-01-02T00:00:00Z");
+        pin_ghost_commit(repo, &second)?;
+        let first = commit_at("2024-01-01T00:00:00Z");
+        pin_ghost_commit(repo, &first)?;
+
+        assert_eq!(list_ghost_commits(repo)?, vec![second, first]);
+        let log = run_git_stdout(repo, &["log", "--format=%s"]);
+        assert_eq!(log, "initial");
+
+        assert_eq!(clear_ghost_commit_refs(repo)?, 2);
+        assert_eq!(list_ghost_commits(repo)?, Vec::new());
+
+        Ok(())
+    }
+
+    #[test]
+    /// Changed files are listed relative to the snapshot, including untracked files.
+    fn changed_files_vs_snapshot_lists_differences() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+
+        std::fs::write(repo.join("modified.txt"), "initial\n")?;
+        std::fs::write(repo.join("deleted.txt"), "initial\n")?;
+        std::fs::write(repo.join("unchanged.txt"), "initial\n")?;
+        run_git_in(repo, &["add", "."]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+        std::fs::write(repo.join("untracked-kept.txt"), "captured\n")?;
+
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        std::fs::write(repo.join("modified.txt"), "changed\n")?;
+        std::fs::remove_file(repo.join("deleted.txt"))?;
+        std::fs::write(repo.join("added.txt"), "new\n")?;
+
+        let changed = changed_files_vs_snapshot(repo, &ghost)?;
+
+        assert_eq!(
+            changed,
+            vec![
+                ChangedFile {
+                    path: PathBuf::from("added.txt"),
+                    status: ChangeStatus::Added,
+                },
+                ChangedFile {
+                    path: PathBuf::from("deleted.txt"),
+                    status: ChangeStatus::Deleted,
+                },
+                ChangedFile {
+                    path: PathBuf::from("modified.txt"),
+                    status: ChangeStatus::Modified,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// Restoring a ghost commit from a non-git directory fails.
+    fn restore_requires_git_repository() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let err = restore_to_commit(temp.path(), "deadbeef").unwrap_err();
+        assert_matches!(err, GitToolingError::NotAGitRepository { .. });
+    }
+
+    #[test]
+    /// Restoring to an id that does not resolve to a commit fails before running the restore.
+    fn restore_rejects_unknown_commit_ids() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("tracked.txt"), "initial\n")?;
+        run_git_in(repo, &["add", "tracked.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "init",
+            ],
+        );
+        std::fs::write(repo.join("tracked.txt"), "edited\n")?;
+
+        for id in [
+            "0123456789abcdef0123456789abcdef01234567",
+            "HEAD:tracked.txt",
+            "../other-repo",
+            "--source=HEAD",
+        ] {
+            let err = restore_to_commit(repo, id).unwrap_err();
+            assert_matches!(err, GitToolingError::UnknownCommit { id: rejected } if rejected == id);
+        }
+        assert_eq!(
+            std::fs::read_to_string(repo.join("tracked.txt"))?,
+            "edited\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// A short hash naming a single commit resolves to it; one shared by several commits
+    /// is reported as ambiguous instead of being passed to git as-is.
+    fn restore_resolves_short_hashes() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("tracked.txt"), "initial\n")?;
+        run_git_in(repo, &["add", "tracked.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "init",
+            ],
+        );
+        let head = run_git_stdout(repo, &["rev-parse", "HEAD"]);
+        std::fs::write(repo.join("tracked.txt"), "edited\n")?;
+
+        restore_to_commit(repo, &head[..7])?;
+        assert_eq!(
+            std::fs::read_to_string(repo.join("tracked.txt"))?,
+            "initial\n"
+        );
+
+        // Write enough commit objects that two of them share a four-character prefix.
+        let tree = run_git_stdout(repo, &["rev-parse", "HEAD^{tree}"]);
+        let objects = tempfile::tempdir()?;
+        let mut paths = String::new();
+        for index in 0..1000 {
+            let path = objects.path().join(index.to_string());
+            std::fs::write(
+                &path,
+                format!(
+                    "tree {tree}\nauthor A <a@example.com> 0 +0000\ncommitter A <a@example.com> 0 +0000\n\ncommit {index}\n"
+                ),
+            )?;
+            paths.push_str(&format!("{}\n", path.display()));
+        }
+        let hashed = run_git_for_stdout_with_input(
+            repo,
+            ["hash-object", "-t", "commit", "-w", "--stdin-paths"],
+            paths.as_bytes(),
+        )?;
+        let mut seen = HashMap::new();
+        let (first, second) = hashed
+            .lines()
+            .find_map(|sha| {
+                seen.insert(&sha[..4], sha)
+                    .map(|other| (other.to_string(), sha.to_string()))
+            })
+            .expect("1000 commits share a four-character prefix");
+
+        let prefix = &first[..4];
+        let err = restore_to_commit(repo, prefix).unwrap_err();
+        assert_matches!(
+            err,
+            GitToolingError::AmbiguousCommit { id, candidates }
+                if id == prefix && candidates.contains(&first) && candidates.contains(&second)
+        );
+        let err = restore_to_commit(repo, "fff").unwrap_err();
+        assert_matches!(err, GitToolingError::UnknownCommit { .. });
+
+        Ok(())
+    }
+
+    #[test]
+    /// Restoring selected paths leaves every other file as it is.
+    fn restore_paths_restores_only_requested_files() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::create_dir_all(repo.join("nested"))?;
+        std::fs::write(repo.join("restore-me.txt"), "initial\n")?;
+        std::fs::write(repo.join("keep-me.txt"), "initial\n")?;
+        std::fs::write(repo.join("nested/file.txt"), "initial\n")?;
+        run_git_in(repo, &["add", "."]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "init",
+            ],
+        );
+
+        std::fs::write(repo.join("restore-me.txt"), "snapshot\n")?;
+        std::fs::write(repo.join("keep-me.txt"), "snapshot\n")?;
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        std::fs::write(repo.join("restore-me.txt"), "edited\n")?;
+        std::fs::write(repo.join("keep-me.txt"), "edited\n")?;
+        std::fs::write(repo.join("nested/file.txt"), "edited\n")?;
+
+        restore_paths(
+            &repo.join("nested"),
+            &ghost,
+            &[
+                PathBuf::from("../restore-me.txt"),
+                repo.join("nested/file.txt"),
+            ],
+        )?;
+
+        assert_eq!(
+            std::fs::read_to_string(repo.join("restore-me.txt"))?,
+            "snapshot\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.join("nested/file.txt"))?,
+            "initial\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.join("keep-me.txt"))?,
+            "edited\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    /// Diffing two snapshots shows only what changed between them.
+    fn diff_ghost_commits_compares_snapshots() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_test_repo(repo);
+        std::fs::write(repo.join("changed.txt"), "first\n")?;
+        std::fs::write(repo.join("same.txt"), "same\n")?;
+        let first = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        std::fs::write(repo.join("changed.txt"), "second\n")?;
+        let second = create_ghost_commit(&CreateGhostCommitOptions::new(repo))?;
+
+        let diff = diff_ghost_commits(repo, &first, &second)?;
+
+        assert!(diff.contains("diff --git a/changed.txt b/changed.txt"));
+        assert!(diff.contains("-first"));
+        assert!(diff.contains("+second"));
+        assert!(!diff.contains("same.txt"));
+        assert_eq!(diff_ghost_commits(repo, &first, &first)?, "");
+
+        Ok(())
+    }
+
+    #[test]
+    /// Paths that resolve outside the repository are rejected before anything is restored.
+    fn restore_paths_rejects_paths_outside_repository() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path().join("repo");
+        std::fs::create_dir(&repo)?;
+        init_test_repo(&repo);
+        std::fs::write(repo.join("tracked.txt"), "initial\n")?;
+        let ghost = create_ghost_commit(&CreateGhostCommitOptions::new(&repo))?;
+        std::fs::write(temp.path().join("outside.txt"), "outside\n")?;
+
+        for path in [
+            PathBuf::from("../outside.txt"),
+            temp.path().join("outside.txt"),
+        ] {
+            let err = restore_paths(&repo, &ghost, std::slice::from_ref(&path)).unwrap_err();
+            assert_matches!(
+                err,
+                GitToolingError::PathOutsideRepository { path: rejected } if rejected == path
+            );
+        }
+
+        Ok(())
     }
 
     #[test]
@@ -1,8 +1,15 @@
+use std::ffi::OsString;
 use std::fmt;
 use std::path::Path;
+use std::path::PathBuf;
 
+use codex_core::git_info::pending_operation;
 use codex_core::revision_control::RevisionControlKind;
 use codex_core::revision_control::RevisionControlSystem;
+use codex_git_apply::extract_paths_from_patch;
+use codex_git_apply::parse_git_apply_output;
+use serde::Deserialize;
+use serde::Serialize;
 
 mod errors;
 mod ghost_commits;
@@ -10,20 +17,45 @@ mod operations;
 mod platform;
 
 pub use errors::GitToolingError;
+pub use errors::SnapshotError;
+pub use ghost_commits::ChangeStatus;
+pub use ghost_commits::ChangedFile;
 pub use ghost_commits::CreateGhostCommitOptions;
+pub use ghost_commits::RestorePlan;
+pub use ghost_commits::RestoreReport;
+pub use ghost_commits::StorageUsage;
 pub use platform::create_symlink;
 
+/// Number of hex digits shown by [`GhostCommit::short_id`].
+const SHORT_ID_LEN: usize = 8;
+
 /// Details of a ghost commit created from a repository state.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Serializable so callers can persist snapshot handles (e.g. an undo stack) across runs.
+/// Restoring a handle whose commit no longer exists fails with
+/// [`GitToolingError::UnknownCommit`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GhostCommit {
     id: String,
     parent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_commit: Option<String>,
 }
 
 impl GhostCommit {
     /// Create a new ghost commit wrapper from a raw commit ID and optional parent.
     pub fn new(id: String, parent: Option<String>) -> Self {
-        Self { id, parent }
+        Self {
+            id,
+            parent,
+            index_commit: None,
+        }
+    }
+
+    /// Attach the commit recording the index captured alongside the snapshot.
+    pub fn with_index_commit(mut self, index_commit: Option<String>) -> Self {
+        self.index_commit = index_commit;
+        self
     }
 
     /// Commit ID for the snapshot.
@@ -31,10 +63,26 @@ impl GhostCommit {
         &self.id
     }
 
+    /// Abbreviated commit ID suitable for user-facing messages.
+    pub fn short_id(&self) -> &str {
+        self.id.get(..SHORT_ID_LEN).unwrap_or(&self.id)
+    }
+
     /// Parent commit ID, if the repository had a `HEAD` at creation time.
     pub fn parent(&self) -> Option<&str> {
         self.parent.as_deref()
     }
+
+    /// Commit recording the index (staged state), when the snapshot was created with
+    /// [`CreateGhostCommitOptions::capture_index`].
+    pub fn index_commit(&self) -> Option<&str> {
+        self.index_commit.as_deref()
+    }
+
+    /// Revision control backend the snapshot was captured from.
+    pub fn kind(&self) -> RevisionControlKind {
+        RevisionControlKind::Git
+    }
 }
 
 impl fmt::Display for GhostCommit {
@@ -55,23 +103,309 @@ impl<'a> RepoSnapshotManager<'a> {
     }
 
     /// Create a snapshot of the repository's working tree.
+    ///
+    /// The snapshot is pinned under `refs/codex/snapshots/` so it survives `git gc` and
+    /// remains available to [`Self::list_snapshots`] in later sessions. Fails with
+    /// [`GitToolingError::OperationInProgress`] while a merge, rebase, or cherry-pick is
+    /// underway, since the working tree and index are then only partially updated.
     pub fn create_snapshot(
         &self,
         options: &CreateGhostCommitOptions<'_>,
     ) -> Result<GhostCommit, GitToolingError> {
-        self.with_git(|| ghost_commits::create_ghost_commit(options))
+        self.with_git(|| {
+            if let Some(operation) = pending_operation(options.repo_path) {
+                return Err(GitToolingError::OperationInProgress {
+                    path: options.repo_path.to_path_buf(),
+                    operation,
+                });
+            }
+            let commit = ghost_commits::create_ghost_commit(options)?;
+            ghost_commits::pin_ghost_commit(options.repo_path, &commit)?;
+            Ok(commit)
+        })
+    }
+
+    /// List snapshots pinned in the repository, newest first.
+    pub fn list_snapshots(&self, repo_path: &Path) -> Result<Vec<GhostCommit>, GitToolingError> {
+        self.with_git(|| ghost_commits::list_ghost_commits(repo_path))
+    }
+
+    /// Number of pinned snapshots in this manager's repository and the bytes of object
+    /// storage only they keep alive.
+    pub fn storage_usage(&self) -> Result<StorageUsage, GitToolingError> {
+        self.with_git(|| ghost_commits::snapshot_storage_usage(self.backend.root()))
+    }
+
+    /// Remove all snapshot refs so `git gc` can reclaim the ghost commits. Returns the
+    /// number of snapshots unpinned.
+    pub fn clear_snapshot_refs(&self, repo_path: &Path) -> Result<usize, GitToolingError> {
+        self.with_git(|| ghost_commits::clear_ghost_commit_refs(repo_path))
+    }
+
+    /// Whether `commit` was captured from the same kind of backend as this manager's, and
+    /// can therefore be restored by it.
+    pub fn can_restore(&self, commit: &GhostCommit) -> bool {
+        commit.kind() == self.backend.kind()
+    }
+
+    /// Whether the repository treats paths differing only by case as the same file
+    /// (`core.ignorecase`). [`RestorePlan::case_collisions`] is only populated when it does.
+    pub fn ignores_case(&self, repo_path: &Path) -> Result<bool, GitToolingError> {
+        self.with_git(|| operations::ignores_case(repo_path))
+    }
+
+    /// Whether the repository has `git maintenance` configured to run in the background,
+    /// where scheduled `gc` prunes unreferenced objects. Ghost commits that must outlive
+    /// the session should then come from [`Self::create_snapshot`], which pins them under
+    /// `refs/codex/snapshots/`, rather than being left dangling.
+    pub fn background_maintenance_configured(
+        &self,
+        repo_path: &Path,
+    ) -> Result<bool, GitToolingError> {
+        self.with_git(|| operations::background_maintenance_configured(repo_path))
     }
 
     /// Restore the working tree to the provided snapshot.
+    ///
+    /// `repo_path` may be a different repository from the one the snapshot was captured
+    /// in (e.g. a scratch clone); the ghost commit is fetched from this manager's root
+    /// first when the target lacks it. Paths are restored relative to `repo_path`, so a
+    /// snapshot taken in a subdirectory needs the same layout in the target.
     pub fn restore_snapshot(
         &self,
         repo_path: &Path,
         commit: &GhostCommit,
     ) -> Result<(), GitToolingError> {
-        self.with_git(|| ghost_commits::restore_ghost_commit(repo_path, commit))
+        self.with_compatible_snapshot(commit, || {
+            ghost_commits::import_ghost_commit(self.backend.root(), repo_path, commit)?;
+            ghost_commits::restore_ghost_commit(repo_path, commit)
+        })
+    }
+
+    /// Restore the working tree to the provided snapshot, including the staged state when
+    /// the snapshot captured it. Like [`Self::restore_snapshot`], `repo_path` may be
+    /// another clone of the repository.
+    pub fn restore_snapshot_with_index(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<(), GitToolingError> {
+        self.with_compatible_snapshot(commit, || {
+            ghost_commits::import_ghost_commit(self.backend.root(), repo_path, commit)?;
+            ghost_commits::restore_ghost_commit_with_index(repo_path, commit)
+        })
+    }
+
+    /// Restore the working tree to the provided snapshot and report which files the
+    /// restore modified, created, or deleted (e.g. to show "restored 4 files"). Like
+    /// [`Self::restore_snapshot`], `repo_path` may be another clone of the repository.
+    pub fn restore_snapshot_reporting(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<RestoreReport, GitToolingError> {
+        self.with_compatible_snapshot(commit, || {
+            ghost_commits::import_ghost_commit(self.backend.root(), repo_path, commit)?;
+            ghost_commits::restore_ghost_commit_reporting(repo_path, commit)
+        })
+    }
+
+    /// Restore only `paths` from the snapshot, leaving other files untouched.
+    ///
+    /// Paths are relative to `repo_path` (or absolute) and must resolve inside the
+    /// repository; anything else fails with [`GitToolingError::PathOutsideRepository`].
+    /// Backends that do not report `supports_partial_restore` in their capabilities fail
+    /// with [`GitToolingError::UnsupportedRevisionControl`].
+    pub fn restore_paths(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+        paths: &[PathBuf],
+    ) -> Result<(), GitToolingError> {
+        self.with_compatible_snapshot(commit, || {
+            if !self.backend.capabilities().supports_partial_restore {
+                return Err(GitToolingError::UnsupportedRevisionControl {
+                    kind: self.backend.kind(),
+                });
+            }
+            ghost_commits::restore_paths(repo_path, commit, paths)
+        })
+    }
+
+    /// Whether the commit the snapshot was taken on top of no longer exists (e.g. after a
+    /// rebase and `git gc`), so the UI can warn before restoring it.
+    pub fn snapshot_is_orphaned(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<bool, GitToolingError> {
+        self.with_compatible_snapshot(commit, || {
+            ghost_commits::ghost_commit_is_orphaned(repo_path, commit)
+        })
+    }
+
+    /// Commit message the snapshot was recorded with, e.g. for display in the TUI.
+    pub fn snapshot_message(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<String, GitToolingError> {
+        self.with_compatible_snapshot(commit, || {
+            ghost_commits::ghost_commit_message(repo_path, commit)
+        })
+    }
+
+    /// Unified diff of what changed between snapshot `a` and snapshot `b`.
+    ///
+    /// Both snapshots must come from this manager's backend; otherwise this fails with
+    /// [`GitToolingError::MismatchedSnapshot`].
+    pub fn diff_snapshots(
+        &self,
+        a: &GhostCommit,
+        b: &GhostCommit,
+    ) -> Result<String, GitToolingError> {
+        self.with_compatible_snapshot(a, || {
+            self.with_compatible_snapshot(b, || {
+                ghost_commits::diff_ghost_commits(self.backend.root(), a, b)
+            })
+        })
+    }
+
+    /// List the files whose current state differs from the snapshot.
+    pub fn changed_files_vs_snapshot(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<Vec<ChangedFile>, GitToolingError> {
+        self.with_compatible_snapshot(commit, || {
+            ghost_commits::changed_files_vs_snapshot(repo_path, commit)
+        })
+    }
+
+    /// Report what restoring the snapshot would change, without modifying anything.
+    pub fn restore_plan(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<RestorePlan, GitToolingError> {
+        self.with_compatible_snapshot(commit, || ghost_commits::restore_plan(repo_path, commit))
+    }
+
+    /// List the files restoring the snapshot would overwrite, create, or delete.
+    ///
+    /// This is a dry run: the working tree is not modified.
+    pub fn preview_restore(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<Vec<PathBuf>, GitToolingError> {
+        self.restore_plan(repo_path, commit)
+            .map(|plan| plan.touched_paths())
+    }
+
+    /// Recreate files present in the snapshot but missing from the working tree.
+    ///
+    /// Existing files are left untouched. Returns the recreated paths relative to `repo_path`.
+    pub fn restore_missing_files(
+        &self,
+        repo_path: &Path,
+        commit: &GhostCommit,
+    ) -> Result<Vec<PathBuf>, GitToolingError> {
+        self.with_compatible_snapshot(commit, || {
+            ghost_commits::restore_missing_files(repo_path, commit)
+        })
+    }
+
+    /// Discard uncommitted changes under `repo_path`: revert tracked files to `HEAD` and
+    /// remove untracked files that are not ignored. Returns the affected paths.
+    ///
+    /// When `dry_run` is true nothing is modified; the returned paths are what would be
+    /// discarded, so callers can ask for confirmation first.
+    pub fn discard_changes(
+        &self,
+        repo_path: &Path,
+        dry_run: bool,
+    ) -> Result<Vec<PathBuf>, GitToolingError> {
+        self.with_git(|| ghost_commits::discard_changes(repo_path, dry_run))
+    }
+
+    /// Snapshot the working tree, run `op`, and restore the snapshot if `op` fails.
+    ///
+    /// A panic inside `op` also rolls the working tree back before unwinding continues.
+    /// The snapshot is created as by [`Self::create_snapshot`] and stays pinned whether
+    /// or not `op` succeeds. On success it is returned with `op`'s value so the change
+    /// can still be undone later, or released with [`Self::clear_snapshot_refs`].
+    pub fn with_snapshot<T, E>(
+        &self,
+        repo_path: &Path,
+        options: &CreateGhostCommitOptions<'_>,
+        op: impl FnOnce() -> Result<T, E>,
+    ) -> Result<(T, GhostCommit), SnapshotError<E>> {
+        let snapshot = self.create_snapshot(options)?;
+        let mut guard = RollbackGuard {
+            manager: self,
+            repo_path,
+            snapshot: &snapshot,
+            armed: true,
+        };
+        let result = op();
+        guard.armed = false;
+        match result {
+            Ok(value) => Ok((value, snapshot)),
+            Err(error) => match self.restore_snapshot(repo_path, &snapshot) {
+                Ok(()) => Err(SnapshotError::RolledBack(error)),
+                Err(restore) => Err(SnapshotError::RollbackFailed { error, restore }),
+            },
+        }
+    }
+
+    /// Apply a unified diff (paths relative to the repository root) to the working
+    /// tree with plain `git apply`; the index is left alone.
+    ///
+    /// With `check_only`, nothing is modified; the call runs `git apply --check` and only
+    /// reports whether the patch applies. A patch naming a path outside the repository
+    /// is refused with [`GitToolingError::PathOutsideRepository`] before git sees it. A
+    /// patch that does not apply cleanly fails with [`GitToolingError::PatchRejected`]
+    /// listing the rejected paths, and the working tree is left untouched.
+    pub fn apply_patch(
+        &self,
+        repo_path: &Path,
+        patch: &str,
+        check_only: bool,
+    ) -> Result<(), GitToolingError> {
+        self.with_git(|| {
+            operations::ensure_git_repository(repo_path)?;
+            for path in extract_paths_from_patch(patch) {
+                operations::normalize_relative_path(Path::new(&path))
+                    .map_err(|_| GitToolingError::PathOutsideRepository { path: path.into() })?;
+            }
+
+            let root = operations::resolve_repository_root(repo_path)?;
+            let mut args = vec![OsString::from("apply")];
+            if check_only {
+                args.push(OsString::from("--check"));
+            }
+            let stderr =
+                match operations::run_git_for_stdout_with_input(&root, args, patch.as_bytes()) {
+                    Ok(_) => return Ok(()),
+                    Err(GitToolingError::GitCommand { stderr, .. }) => stderr,
+                    Err(other) => return Err(other),
+                };
+            let (_, skipped, conflicted) = parse_git_apply_output("", &stderr);
+            let mut rejected = conflicted;
+            rejected.extend(skipped);
+            if rejected.is_empty() {
+                rejected.push(stderr.trim().to_string());
+            }
+            Err(GitToolingError::PatchRejected { rejected })
+        })
     }
 
     /// Restore the working tree to the provided commit id.
+    ///
+    /// Abbreviated hashes are resolved first; one matching several commits fails with
+    /// [`GitToolingError::AmbiguousCommit`] and one matching none with
+    /// [`GitToolingError::UnknownCommit`].
     pub fn restore_to_commit(
         &self,
         repo_path: &Path,
@@ -80,10 +414,30 @@ impl<'a> RepoSnapshotManager<'a> {
         self.with_git(|| ghost_commits::restore_to_commit(repo_path, commit_id))
     }
 
+    fn with_compatible_snapshot<T>(
+        &self,
+        commit: &GhostCommit,
+        op: impl FnOnce() -> Result<T, GitToolingError>,
+    ) -> Result<T, GitToolingError> {
+        if !self.can_restore(commit) {
+            return Err(GitToolingError::MismatchedSnapshot {
+                short_id: commit.short_id().to_string(),
+                snapshot_kind: commit.kind(),
+                backend_kind: self.backend.kind(),
+            });
+        }
+        self.with_git(op)
+    }
+
     fn with_git<T>(
         &self,
         op: impl FnOnce() -> Result<T, GitToolingError>,
     ) -> Result<T, GitToolingError> {
+        if self.backend.is_bare() {
+            return Err(GitToolingError::BareRepository {
+                path: self.backend.root().to_path_buf(),
+            });
+        }
         match self.backend.kind() {
             RevisionControlKind::Git => op(),
             other => Err(GitToolingError::UnsupportedRevisionControl { kind: other }),
@@ -91,9 +445,30 @@ impl<'a> RepoSnapshotManager<'a> {
     }
 }
 
+/// Restores a snapshot when dropped while still armed, i.e. when the operation
+/// guarded by [`RepoSnapshotManager::with_snapshot`] panicked.
+struct RollbackGuard<'m, 'a> {
+    manager: &'m RepoSnapshotManager<'a>,
+    repo_path: &'m Path,
+    snapshot: &'m GhostCommit,
+    armed: bool,
+}
+
+impl Drop for RollbackGuard<'_, '_> {
+    fn drop(&mut self) {
+        if self.armed {
+            // Nothing can be reported while unwinding; the snapshot stays pinned so the
+            // restore can be retried by hand.
+            let _ = self.manager.restore_snapshot(self.repo_path, self.snapshot);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assert_matches::assert_matches;
+    use codex_core::git_info::PendingOperation;
     use codex_core::revision_control::DetectedRevisionControl;
     use codex_core::revision_control::RevisionControlCapabilities;
     use codex_core::revision_control::RevisionControlKind;
@@ -105,8 +480,26 @@ mod tests {
         DetectedRevisionControl {
             kind: RevisionControlKind::Git,
             root: root.to_path_buf(),
-            capabilities: RevisionControlCapabilities::new(true, true),
+            capabilities: RevisionControlCapabilities::new(true, true).with_partial_restore(true),
             tooling_error: None,
+            is_bare: false,
+        }
+    }
+
+    #[test]
+    fn manager_rejects_bare_repositories() {
+        let backend = DetectedRevisionControl::new_bare_git(PathBuf::from("/tmp/repo.git"));
+        let manager = RepoSnapshotManager::new(&backend);
+        let options = CreateGhostCommitOptions::new(Path::new("/tmp/repo.git"));
+        let err = manager
+            .create_snapshot(&options)
+            .expect_err("expected bare repository error");
+
+        match err {
+            GitToolingError::BareRepository { path } => {
+                assert_eq!(path, PathBuf::from("/tmp/repo.git"));
+            }
+            other => panic!("unexpected error: {other}"),
         }
     }
 
@@ -143,6 +536,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn restore_paths_requires_partial_restore_capability() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+        let backend = DetectedRevisionControl {
+            capabilities: RevisionControlCapabilities::new(true, true),
+            ..git_backend(repo)
+        };
+        let manager = RepoSnapshotManager::new(&backend);
+        let commit = GhostCommit::new("deadbeef".to_string(), None);
+
+        let err = manager
+            .restore_paths(repo, &commit, &[PathBuf::from("file.txt")])
+            .expect_err("expected unsupported backend error");
+
+        match err {
+            GitToolingError::UnsupportedRevisionControl { kind } => {
+                assert_eq!(kind, RevisionControlKind::Git);
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn can_restore_checks_snapshot_backend() {
+        struct Dummy;
+
+        impl RevisionControlSystem for Dummy {
+            fn kind(&self) -> RevisionControlKind {
+                RevisionControlKind::Darcs
+            }
+
+            fn root(&self) -> &Path {
+                Path::new("/tmp")
+            }
+
+            fn capabilities(&self) -> RevisionControlCapabilities {
+                RevisionControlCapabilities::new(false, false)
+            }
+        }
+
+        let commit = GhostCommit::new("0123456789abcdef".to_string(), None);
+        let git = git_backend(Path::new("/tmp"));
+        assert!(RepoSnapshotManager::new(&git).can_restore(&commit));
+
+        let darcs = Dummy;
+        let manager = RepoSnapshotManager::new(&darcs);
+        assert!(!manager.can_restore(&commit));
+
+        let err = manager
+            .restore_snapshot(Path::new("/tmp"), &commit)
+            .expect_err("expected mismatched snapshot error");
+        match err {
+            GitToolingError::MismatchedSnapshot {
+                short_id,
+                snapshot_kind,
+                backend_kind,
+            } => {
+                assert_eq!(short_id, "01234567");
+                assert_eq!(snapshot_kind, RevisionControlKind::Git);
+                assert_eq!(backend_kind, RevisionControlKind::Darcs);
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn ghost_commit_round_trips_through_json() {
+        let commit = GhostCommit::new("0123456789abcdef".to_string(), Some("fedcba".to_string()));
+
+        let json = serde_json::to_string(&commit).unwrap();
+
+        assert_eq!(json, r#"{"id":"0123456789abcdef","parent":"fedcba"}"#);
+        assert_eq!(serde_json::from_str::<GhostCommit>(&json).unwrap(), commit);
+    }
+
     #[test]
     fn manager_creates_and_restores_snapshots() -> Result<(), GitToolingError> {
         let temp_dir = tempdir().unwrap();
@@ -179,4 +648,348 @@ mod tests {
         assert_eq!(restored, "modified");
         Ok(())
     }
+
+    #[test]
+    /// Snapshots are refused while git is midway through a merge, rebase, or cherry-pick.
+    fn create_snapshot_refuses_pending_operations() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+        Command::new("git")
+            .args(["init", "--initial-branch", "main"])
+            .current_dir(repo)
+            .status()
+            .expect("git init must succeed");
+        std::fs::write(repo.join("test.txt"), "initial").unwrap();
+
+        let backend = git_backend(repo);
+        let manager = RepoSnapshotManager::new(&backend);
+        let options = CreateGhostCommitOptions::new(repo);
+        let git_dir = repo.join(".git");
+        let cases = [
+            ("MERGE_HEAD", false, PendingOperation::Merge),
+            ("CHERRY_PICK_HEAD", false, PendingOperation::CherryPick),
+            ("rebase-merge", true, PendingOperation::Rebase),
+            ("rebase-apply", true, PendingOperation::Rebase),
+        ];
+        for (marker, is_dir, expected) in cases {
+            let marker = git_dir.join(marker);
+            if is_dir {
+                std::fs::create_dir(&marker).unwrap();
+            } else {
+                std::fs::write(&marker, "0000000000000000000000000000000000000000\n").unwrap();
+            }
+
+            match manager.create_snapshot(&options) {
+                Err(GitToolingError::OperationInProgress { path, operation }) => {
+                    assert_eq!(path, repo);
+                    assert_eq!(operation, expected);
+                }
+                other => panic!("unexpected result for {marker:?}: {other:?}"),
+            }
+
+            if is_dir {
+                std::fs::remove_dir(&marker).unwrap();
+            } else {
+                std::fs::remove_file(&marker).unwrap();
+            }
+        }
+
+        manager
+            .create_snapshot(&options)
+            .expect("snapshot succeeds once the operation is over");
+    }
+
+    #[test]
+    fn with_snapshot_rolls_back_failures_and_panics() -> Result<(), GitToolingError> {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+
+        Command::new("git")
+            .args(["init", "--initial-branch", "main"])
+            .current_dir(repo)
+            .status()
+            .expect("git init must succeed");
+        std::fs::write(repo.join("test.txt"), "initial").unwrap();
+
+        let backend = git_backend(repo);
+        let manager = RepoSnapshotManager::new(&backend);
+        let options = CreateGhostCommitOptions::new(repo);
+
+        let result = manager.with_snapshot(repo, &options, || {
+            std::fs::write(repo.join("test.txt"), "failed edit")?;
+            Err::<(), _>(std::io::Error::other("edit failed"))
+        });
+        match result {
+            Err(SnapshotError::RolledBack(err)) => assert_eq!(err.to_string(), "edit failed"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert_eq!(
+            std::fs::read_to_string(repo.join("test.txt")).unwrap(),
+            "initial"
+        );
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            manager.with_snapshot(repo, &options, || -> Result<(), std::io::Error> {
+                std::fs::write(repo.join("test.txt"), "panicking edit")?;
+                panic!("edit panicked");
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(
+            std::fs::read_to_string(repo.join("test.txt")).unwrap(),
+            "initial"
+        );
+
+        let value = manager.with_snapshot(repo, &options, || {
+            std::fs::write(repo.join("test.txt"), "kept edit")?;
+            Ok::<_, std::io::Error>(42)
+        });
+        let (value, snapshot) = value.unwrap();
+        assert_eq!(value, 42);
+        assert_eq!(
+            std::fs::read_to_string(repo.join("test.txt")).unwrap(),
+            "kept edit"
+        );
+
+        manager.restore_snapshot(repo, &snapshot)?;
+        assert_eq!(
+            std::fs::read_to_string(repo.join("test.txt")).unwrap(),
+            "initial"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn manager_restores_snapshots_into_another_clone() -> Result<(), GitToolingError> {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let scratch = temp_dir.path().join("scratch");
+        std::fs::create_dir(&source).unwrap();
+
+        let git = |dir: &Path, args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .status()
+                .expect("git must run");
+            assert!(status.success(), "git {args:?} failed");
+        };
+        git(&source, &["init", "--initial-branch", "main"]);
+        std::fs::write(source.join("test.txt"), "initial").unwrap();
+        git(&source, &["add", "test.txt"]);
+        git(
+            &source,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+
+        std::fs::write(source.join("test.txt"), "modified").unwrap();
+        std::fs::write(source.join("new.txt"), "untracked").unwrap();
+        let backend = git_backend(&source);
+        let manager = RepoSnapshotManager::new(&backend);
+        let snapshot = manager.create_snapshot(&CreateGhostCommitOptions::new(&source))?;
+
+        // `--no-local` copies objects instead of hard-linking them, so the scratch clone
+        // starts without the ghost commit.
+        git(
+            temp_dir.path(),
+            &[
+                "clone",
+                "--no-local",
+                source.to_str().unwrap(),
+                scratch.to_str().unwrap(),
+            ],
+        );
+        manager.restore_snapshot(&scratch, &snapshot)?;
+
+        assert_eq!(
+            std::fs::read_to_string(scratch.join("test.txt")).unwrap(),
+            "modified"
+        );
+        assert_eq!(
+            std::fs::read_to_string(scratch.join("new.txt")).unwrap(),
+            "untracked"
+        );
+
+        let reporting_scratch = temp_dir.path().join("reporting-scratch");
+        git(
+            temp_dir.path(),
+            &[
+                "clone",
+                "--no-local",
+                source.to_str().unwrap(),
+                reporting_scratch.to_str().unwrap(),
+            ],
+        );
+        let report = manager.restore_snapshot_reporting(&reporting_scratch, &snapshot)?;
+
+        assert_eq!(report.modified, vec![PathBuf::from("test.txt")]);
+        assert_eq!(report.created, vec![PathBuf::from("new.txt")]);
+        assert_eq!(
+            std::fs::read_to_string(reporting_scratch.join("new.txt")).unwrap(),
+            "untracked"
+        );
+        Ok(())
+    }
+
+    /// Runs a git command in the test repository and asserts success.
+    fn run_git_in(repo_path: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .current_dir(repo_path)
+            .args(args)
+            .status()
+            .expect("git command");
+        assert!(status.success(), "git command failed: {args:?}");
+    }
+
+    /// Initializes a repository with `greeting.txt` committed.
+    fn init_repo_with_greeting(repo: &Path) {
+        run_git_in(repo, &["init", "--initial-branch=main"]);
+        run_git_in(repo, &["config", "core.autocrlf", "false"]);
+        std::fs::write(repo.join("greeting.txt"), "hello\nworld\n").unwrap();
+        run_git_in(repo, &["add", "greeting.txt"]);
+        run_git_in(
+            repo,
+            &[
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "initial",
+            ],
+        );
+    }
+
+    const GREETING_PATCH: &str = "\
+diff --git a/greeting.txt b/greeting.txt
+--- a/greeting.txt
++++ b/greeting.txt
+@@ -1,2 +1,2 @@
+ hello
+-world
++there
+";
+
+    #[test]
+    /// A clean patch is applied to the working tree.
+    fn applies_clean_patch() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_repo_with_greeting(repo);
+
+        RepoSnapshotManager::new(&git_backend(repo)).apply_patch(repo, GREETING_PATCH, false)?;
+
+        assert_eq!(
+            std::fs::read_to_string(repo.join("greeting.txt"))?,
+            "hello\nthere\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// `check_only` validates the patch without touching the working tree.
+    fn check_only_leaves_working_tree_untouched() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_repo_with_greeting(repo);
+
+        RepoSnapshotManager::new(&git_backend(repo)).apply_patch(repo, GREETING_PATCH, true)?;
+
+        assert_eq!(
+            std::fs::read_to_string(repo.join("greeting.txt"))?,
+            "hello\nworld\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// A patch that no longer matches the file reports the rejected hunk.
+    fn conflicting_patch_is_rejected() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_repo_with_greeting(repo);
+        std::fs::write(repo.join("greeting.txt"), "goodbye\nworld\n")?;
+
+        let err = RepoSnapshotManager::new(&git_backend(repo))
+            .apply_patch(repo, GREETING_PATCH, false)
+            .unwrap_err();
+
+        assert_matches!(
+            err,
+            GitToolingError::PatchRejected { ref rejected }
+                if rejected.iter().any(|line| line.contains("greeting.txt"))
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.join("greeting.txt"))?,
+            "goodbye\nworld\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// A real `git diff` patch (with `index` lines) that conflicts is rejected without
+    /// touching the working tree or the index.
+    fn conflicting_git_diff_patch_is_rejected() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_repo_with_greeting(repo);
+        std::fs::write(repo.join("greeting.txt"), "hello\nthere\n")?;
+        let output = Command::new("git")
+            .current_dir(repo)
+            .args(["diff", "--full-index"])
+            .output()?;
+        let patch = String::from_utf8(output.stdout).expect("utf-8 diff");
+        assert!(patch.contains("\nindex "), "expected index line in {patch}");
+        run_git_in(repo, &["checkout", "--", "greeting.txt"]);
+        std::fs::write(repo.join("greeting.txt"), "goodbye\nworld\n")?;
+
+        let err = RepoSnapshotManager::new(&git_backend(repo))
+            .apply_patch(repo, &patch, false)
+            .unwrap_err();
+
+        assert_matches!(
+            err,
+            GitToolingError::PatchRejected { ref rejected }
+                if rejected.iter().any(|line| line.contains("greeting.txt"))
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.join("greeting.txt"))?,
+            "goodbye\nworld\n"
+        );
+        let staged = Command::new("git")
+            .current_dir(repo)
+            .args(["diff", "--cached", "--name-only"])
+            .output()?;
+        assert!(staged.stdout.is_empty(), "index was modified");
+        Ok(())
+    }
+
+    #[test]
+    /// Paths escaping the repository are refused before running git.
+    fn patch_escaping_repository_is_refused() -> Result<(), GitToolingError> {
+        let temp = tempfile::tempdir()?;
+        let repo = temp.path();
+        init_repo_with_greeting(repo);
+        let patch = GREETING_PATCH.replace("b/greeting.txt", "b/../outside.txt");
+
+        let err = RepoSnapshotManager::new(&git_backend(repo))
+            .apply_patch(repo, &patch, true)
+            .unwrap_err();
+
+        assert_matches!(
+            err,
+            GitToolingError::PathOutsideRepository { path }
+                if path == PathBuf::from("../outside.txt")
+        );
+        Ok(())
+    }
 }
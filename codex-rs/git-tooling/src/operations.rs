@@ -1,9 +1,13 @@
 use std::ffi::OsStr;
 use std::ffi::OsString;
+use std::io::Write;
 use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
+use std::process::Stdio;
+
+use codex_core::revision_control::git::git_program;
 
 use crate::GitToolingError;
 
@@ -45,6 +49,45 @@ pub(crate) fn resolve_head(path: &Path) -> Result<Option<String>, GitToolingErro
     }
 }
 
+/// Read a git configuration value visible from `path`, or `None` when `key` is unset.
+///
+/// `value_type` is forwarded to `git config --type` (e.g. `bool`) so git normalizes the
+/// value before it is returned.
+pub(crate) fn read_git_config(
+    path: &Path,
+    key: &str,
+    value_type: Option<&str>,
+) -> Result<Option<String>, GitToolingError> {
+    let mut args = vec![OsString::from("config")];
+    if let Some(value_type) = value_type {
+        args.push(OsString::from(format!("--type={value_type}")));
+    }
+    args.extend([OsString::from("--get"), OsString::from(key)]);
+    match run_git_for_stdout(path, args, None) {
+        Ok(value) => Ok(Some(value)),
+        // `git config --get` exits with 1 when the key is not set.
+        Err(GitToolingError::GitCommand { status, .. }) if status.code() == Some(1) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether the repository treats paths that differ only by case as the same file
+/// (`core.ignorecase`).
+pub(crate) fn ignores_case(path: &Path) -> Result<bool, GitToolingError> {
+    Ok(read_git_config(path, "core.ignorecase", Some("bool"))?.as_deref() == Some("true"))
+}
+
+/// Whether `git maintenance` may garbage collect in the background: a schedule
+/// strategy other than `none` is set (as `git maintenance start` does), or
+/// `maintenance.auto` is explicitly enabled.
+pub(crate) fn background_maintenance_configured(path: &Path) -> Result<bool, GitToolingError> {
+    let strategy = read_git_config(path, "maintenance.strategy", None)?;
+    if strategy.is_some_and(|strategy| strategy != "none") {
+        return Ok(true);
+    }
+    Ok(read_git_config(path, "maintenance.auto", Some("bool"))?.as_deref() == Some("true"))
+}
+
 pub(crate) fn normalize_relative_path(path: &Path) -> Result<PathBuf, GitToolingError> {
     let mut result = PathBuf::new();
     let mut saw_component = false;
@@ -77,6 +120,39 @@ pub(crate) fn normalize_relative_path(path: &Path) -> Result<PathBuf, GitTooling
     Ok(result)
 }
 
+/// Resolve `path` (relative to `repo_path`, or absolute) to a path relative to
+/// `repo_root`, rejecting anything that lands outside the repository.
+pub(crate) fn path_within_repo(
+    repo_root: &Path,
+    repo_path: &Path,
+    path: &Path,
+) -> Result<PathBuf, GitToolingError> {
+    let outside = || GitToolingError::PathOutsideRepository {
+        path: path.to_path_buf(),
+    };
+    let candidate = if path.is_absolute() {
+        match path.strip_prefix(repo_root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => {
+                let repo_root = repo_root.canonicalize()?;
+                let path = path.canonicalize().map_err(|_| outside())?;
+                path.strip_prefix(&repo_root)
+                    .map_err(|_| outside())?
+                    .to_path_buf()
+            }
+        }
+    } else {
+        match repo_subdir(repo_root, repo_path) {
+            Some(prefix) => prefix.join(path),
+            None => path.to_path_buf(),
+        }
+    };
+    normalize_relative_path(&candidate).map_err(|err| match err {
+        GitToolingError::PathEscapesRepository { .. } => outside(),
+        other => other,
+    })
+}
+
 pub(crate) fn resolve_repository_root(path: &Path) -> Result<PathBuf, GitToolingError> {
     let root = run_git_for_stdout(
         path,
@@ -139,7 +215,7 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
 {
-    run_git(dir, args, env)?;
+    run_git(dir, args, env, None)?;
     Ok(())
 }
 
@@ -152,19 +228,30 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
 {
-    let run = run_git(dir, args, env)?;
-    String::from_utf8(run.output.stdout)
-        .map(|value| value.trim().to_string())
-        .map_err(|source| GitToolingError::GitOutputUtf8 {
-            command: run.command,
-            source,
-        })
+    let run = run_git(dir, args, env, None)?;
+    run.stdout()
+}
+
+/// Like [`run_git_for_stdout`] but feeds `input` to git's stdin, for batch commands
+/// such as `git cat-file --batch-check`.
+pub(crate) fn run_git_for_stdout_with_input<I, S>(
+    dir: &Path,
+    args: I,
+    input: &[u8],
+) -> Result<String, GitToolingError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let run = run_git(dir, args, None, Some(input))?;
+    run.stdout()
 }
 
 fn run_git<I, S>(
     dir: &Path,
     args: I,
     env: Option<&[(OsString, OsString)]>,
+    input: Option<&[u8]>,
 ) -> Result<GitRun, GitToolingError>
 where
     I: IntoIterator<Item = S>,
@@ -177,7 +264,7 @@ where
         args_vec.push(OsString::from(arg.as_ref()));
     }
     let command_string = build_command_string(&args_vec);
-    let mut command = Command::new("git");
+    let mut command = Command::new(git_program());
     command.current_dir(dir);
     if let Some(envs) = env {
         for (key, value) in envs {
@@ -185,7 +272,29 @@ where
         }
     }
     command.args(&args_vec);
-    let output = command.output()?;
+    let output = match input {
+        None => command.output()?,
+        Some(input) => {
+            let mut child = command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            let mut stdin = child
+                .stdin
+                .take()
+                .ok_or_else(|| std::io::Error::other("git stdin was not captured"))?;
+            // Write from a separate thread so git never blocks on a full stdout pipe
+            // while we are still feeding it input.
+            std::thread::scope(|scope| {
+                let writer = scope.spawn(move || stdin.write_all(input));
+                let output = child.wait_with_output();
+                // A failed write (e.g. git exiting early) surfaces through git's exit status.
+                let _ = writer.join();
+                output
+            })?
+        }
+    };
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
         return Err(GitToolingError::GitCommand {
@@ -216,3 +325,14 @@ struct GitRun {
     command: String,
     output: std::process::Output,
 }
+
+impl GitRun {
+    fn stdout(self) -> Result<String, GitToolingError> {
+        String::from_utf8(self.output.stdout)
+            .map(|value| value.trim().to_string())
+            .map_err(|source| GitToolingError::GitOutputUtf8 {
+                command: self.command,
+                source,
+            })
+    }
+}
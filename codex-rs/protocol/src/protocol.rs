@@ -1051,18 +1051,159 @@ pub struct GitInfo {
     /// Current commit hash (SHA)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub commit_hash: Option<String>,
+    /// Current commit hash abbreviated the way git does (honoring `core.abbrev`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_commit_hash: Option<String>,
     /// Current branch name
     #[serde(skip_serializing_if = "Option::is_none")]
     pub branch: Option<String>,
+    /// Upstream tracking branch of the current branch (e.g. `origin/main`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
     /// Repository URL (if available from remote)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repository_url: Option<String>,
+    /// `repository_url` split into its parts (if it is a recognized remote URL form)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parsed_remote: Option<RemoteUrl>,
+    /// Tag pointing exactly at the current commit (if any)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Whether the repository stores files with Git LFS
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub uses_lfs: bool,
+    /// Whether the clone is shallow (e.g. `git clone --depth 1`), so history is truncated
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_shallow: bool,
+    /// Number of ignored paths present in the working tree, with fully ignored directories
+    /// counted once. Only collected on request since it walks ignored directories.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignored_count: Option<usize>,
+    /// Submodules of the repository, including nested ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<SubmoduleStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct SubmoduleStatus {
+    /// Path of the submodule relative to the superproject root
+    pub path: String,
+    /// Commit checked out in the submodule, or recorded in the superproject if uninitialized
+    pub sha: String,
+    pub state: SubmoduleState,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmoduleState {
+    /// Checked out at the commit recorded in the superproject
+    InSync,
+    /// Checked out at a different commit, or has merge conflicts
+    Modified,
+    /// Not initialized
+    Uninitialized,
+}
+
+/// Transport named by a remote URL. scp-like URLs (`user@host:path`) count as SSH.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteScheme {
+    Ssh,
+    Https,
+    Http,
+    Git,
+}
+
+/// A remote URL split into the parts downstream consumers usually want.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct RemoteUrl {
+    pub scheme: RemoteScheme,
+    /// Lowercased host name, without user, port, or a leading `www.`
+    pub host: String,
+    /// Everything before the last path segment, e.g. `group/subgroup` on GitLab
+    pub owner: String,
+    /// Last path segment without a trailing `.git`
+    pub repo: String,
+}
+
+impl RemoteUrl {
+    /// Parse `https://`, `http://`, `ssh://`, `git://`, and scp-like `user@host:owner/repo`
+    /// URLs. Percent-escapes in the path (e.g. `%20`) are decoded. Returns `None` for local
+    /// paths (including Windows paths such as `C:/src/repo`), other schemes, and URLs
+    /// without both an owner and a repository name.
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = url.trim();
+        let (scheme, authority, path) = match url.split_once("://") {
+            Some((scheme, rest)) => {
+                let scheme = match scheme.to_ascii_lowercase().as_str() {
+                    "ssh" | "git+ssh" | "ssh+git" => RemoteScheme::Ssh,
+                    "https" => RemoteScheme::Https,
+                    "http" => RemoteScheme::Http,
+                    "git" => RemoteScheme::Git,
+                    _ => return None,
+                };
+                let (authority, path) = rest.split_once('/')?;
+                (scheme, authority, percent_decode(path)?)
+            }
+            None => {
+                let (authority, path) = url.split_once(':')?;
+                // `./dir:x` or `/srv/repo:x` are local paths, not scp-like URLs, and so is
+                // a drive letter: git never reads `C:/src/repo` as host `C`.
+                if authority.contains('/') || authority.len() == 1 {
+                    return None;
+                }
+                (RemoteScheme::Ssh, authority, path.to_string())
+            }
+        };
+
+        let host = authority
+            .rsplit('@')
+            .next()?
+            .split(':')
+            .next()?
+            .to_ascii_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        if host.is_empty() {
+            return None;
+        }
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let (owner, repo) = path.rsplit_once('/')?;
+        if owner.is_empty() || repo.is_empty() {
+            return None;
+        }
+        Some(Self {
+            scheme,
+            host: host.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+        })
+    }
+}
+
+/// Decode `%XX` escapes; `None` if an escape is malformed or the result is not UTF-8.
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, TS)]
 #[serde(rename_all = "snake_case")]
 pub enum RevisionControlBackend {
     Git,
+    Fossil,
     Darcs,
 }
 
@@ -1077,6 +1218,19 @@ pub struct DarcsInfo {
     /// Default remote repository configured for the checkout.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_remote: Option<String>,
+    /// Whether the working tree has unrecorded changes (`None` if it could not be determined).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_pending: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, TS)]
+pub struct FossilInfo {
+    /// Hash of the checked-out check-in (if available).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkout_hash: Option<String>,
+    /// Branch of the checked-out check-in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, TS)]
@@ -1085,9 +1239,14 @@ pub struct RevisionControlSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub git: Option<GitInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub fossil: Option<FossilInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub darcs: Option<DarcsInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tooling_error: Option<String>,
+    /// Uncommitted changes as reported by the backend's diff command, when requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_diff: Option<String>,
 }
 
 /// Review request sent to the review session.
@@ -1451,4 +1610,62 @@ mod tests {
         assert_eq!(deserialized, event);
         Ok(())
     }
+
+    #[test]
+    fn remote_url_parse_splits_common_url_forms() {
+        let remote = |scheme, host: &str, owner: &str, repo: &str| RemoteUrl {
+            scheme,
+            host: host.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+        };
+
+        assert_eq!(
+            RemoteUrl::parse("git@github.com:openai/codex.git"),
+            Some(remote(RemoteScheme::Ssh, "github.com", "openai", "codex"))
+        );
+        assert_eq!(
+            RemoteUrl::parse("https://user@GitHub.com/openai/codex/"),
+            Some(remote(RemoteScheme::Https, "github.com", "openai", "codex"))
+        );
+        assert_eq!(
+            RemoteUrl::parse("https://WWW.github.com/openai/codex"),
+            Some(remote(RemoteScheme::Https, "github.com", "openai", "codex"))
+        );
+        assert_eq!(
+            RemoteUrl::parse("ssh://git@gitlab.example.com:2222/group/sub/project.git"),
+            Some(remote(
+                RemoteScheme::Ssh,
+                "gitlab.example.com",
+                "group/sub",
+                "project"
+            ))
+        );
+        assert_eq!(
+            RemoteUrl::parse("https://example.com/t%C3%A9am/my%20repo.git"),
+            Some(remote(
+                RemoteScheme::Https,
+                "example.com",
+                "téam",
+                "my repo"
+            ))
+        );
+        assert_eq!(
+            RemoteUrl::parse("git@example.com:équipe/dépôt avec espaces.git"),
+            Some(remote(
+                RemoteScheme::Ssh,
+                "example.com",
+                "équipe",
+                "dépôt avec espaces"
+            ))
+        );
+        assert_eq!(
+            RemoteUrl::parse("git://example.com/owner/repo"),
+            Some(remote(RemoteScheme::Git, "example.com", "owner", "repo"))
+        );
+        assert_eq!(RemoteUrl::parse("/srv/git/repo.git"), None);
+        assert_eq!(RemoteUrl::parse("C:/src/owner/repo.git"), None);
+        assert_eq!(RemoteUrl::parse("file:///srv/git/repo.git"), None);
+        assert_eq!(RemoteUrl::parse("https://example.com/repo.git"), None);
+    }
 }
@@ -5,7 +5,9 @@ use std::sync::Arc;
 
 use codex_core::config::Config;
 use codex_core::config_types::Notifications;
+use codex_core::git_info::RepoDiffOptions;
 use codex_core::git_info::current_branch_name;
+use codex_core::git_info::get_repo_diff;
 use codex_core::git_info::local_git_branches;
 use codex_core::protocol::AgentMessageDeltaEvent;
 use codex_core::protocol::AgentMessageEvent;
@@ -42,7 +44,7 @@ use codex_core::protocol::UserMessageEvent;
 use codex_core::protocol::ViewImageToolCallEvent;
 use codex_core::protocol::WebSearchBeginEvent;
 use codex_core::protocol::WebSearchEndEvent;
-use codex_core::revision_control::detect_revision_control;
+use codex_core::revision_control::DetectionCache;
 use codex_protocol::ConversationId;
 use codex_protocol::parse_command::ParsedCommand;
 use crossterm::event::KeyCode;
@@ -76,7 +78,6 @@ use crate::diff_render::display_path_for;
 use crate::exec_cell::CommandOutput;
 use crate::exec_cell::ExecCell;
 use crate::exec_cell::new_active_exec_command;
-use crate::get_repo_diff::get_repo_diff;
 use crate::history_cell;
 use crate::history_cell::AgentMessageCell;
 use crate::history_cell::HistoryCell;
@@ -258,6 +259,8 @@ pub(crate) struct ChatWidget {
     ghost_snapshots_disabled: bool,
     // Whether to add a final message separator after the last message
     needs_final_message_separator: bool,
+    // Revision control detection for the cwd, re-run on every snapshot and undo.
+    detection_cache: DetectionCache,
 
     last_rendered_width: std::cell::Cell<Option<usize>>,
 }
@@ -937,6 +940,7 @@ impl ChatWidget {
             ghost_snapshots: Vec::new(),
             ghost_snapshots_disabled: true,
             needs_final_message_separator: false,
+            detection_cache: DetectionCache::default(),
             last_rendered_width: std::cell::Cell::new(None),
         }
     }
@@ -1000,6 +1004,7 @@ impl ChatWidget {
             ghost_snapshots: Vec::new(),
             ghost_snapshots_disabled: true,
             needs_final_message_separator: false,
+            detection_cache: DetectionCache::default(),
             last_rendered_width: std::cell::Cell::new(None),
         }
     }
@@ -1140,7 +1145,7 @@ impl ChatWidget {
                 self.add_diff_in_progress();
                 let tx = self.app_event_tx.clone();
                 tokio::spawn(async move {
-                    let text = match get_repo_diff().await {
+                    let text = match get_repo_diff(&RepoDiffOptions::default()).await {
                         Ok((Some(kind), diff_text)) => {
                             if diff_text.trim().is_empty() {
                                 format!(
@@ -1297,7 +1302,8 @@ impl ChatWidget {
         }
 
         let options = CreateGhostCommitOptions::new(&self.config.cwd);
-        let result = if let Some(revision_control) = detect_revision_control(&self.config.cwd) {
+        let detected = self.detection_cache.detect(&self.config.cwd);
+        let result = if let Some(revision_control) = detected {
             RepoSnapshotManager::new(&revision_control).create_snapshot(&options)
         } else {
             Err(GitToolingError::NotAGitRepository {
@@ -1350,7 +1356,8 @@ impl ChatWidget {
             return;
         };
 
-        let result = if let Some(revision_control) = detect_revision_control(&self.config.cwd) {
+        let detected = self.detection_cache.detect(&self.config.cwd);
+        let result = if let Some(revision_control) = detected {
             RepoSnapshotManager::new(&revision_control).restore_snapshot(&self.config.cwd, &commit)
         } else {
             Err(GitToolingError::NotAGitRepository {
@@ -1364,7 +1371,7 @@ impl ChatWidget {
             return;
         }
 
-        let short_id: String = commit.id().chars().take(8).collect();
+        let short_id = commit.short_id();
         self.add_info_message(format!("Restored workspace to snapshot {short_id}"), None);
     }
 
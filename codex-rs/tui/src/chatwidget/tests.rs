@@ -286,6 +286,7 @@ fn make_chatwidget_manual() -> (
         ghost_snapshots: Vec::new(),
         ghost_snapshots_disabled: false,
         needs_final_message_separator: false,
+        detection_cache: DetectionCache::default(),
         last_rendered_width: std::cell::Cell::new(None),
     };
     (widget, rx, op_rx)
@@ -769,11 +770,15 @@ fn review_commit_picker_shows_subjects_without_timestamps() {
             sha: "1111111deadbeef".to_string(),
             timestamp: 0,
             subject: "Add new feature X".to_string(),
+            body: String::new(),
+            notes: None,
         },
         codex_core::git_info::CommitLogEntry {
             sha: "2222222cafebabe".to_string(),
             timestamp: 0,
             subject: "Fix bug Y".to_string(),
+            body: String::new(),
+            notes: None,
         },
     ];
     super::show_review_commit_picker_with_entries(&mut chat, entries);
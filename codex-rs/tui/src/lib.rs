@@ -46,7 +46,6 @@ mod exec_cell;
 mod exec_command;
 mod file_search;
 mod frames;
-mod get_repo_diff;
 mod history_cell;
 pub mod insert_history;
 mod key_hint;